@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
 
+use crate::level_definition::LevelDefinition;
+
 #[derive(AssetCollection, Resource)]
 pub struct GameAssets {
     #[asset(path = "player.png")]
@@ -19,6 +21,18 @@ pub struct GameAssets {
     pub teleport: Handle<Image>,
     #[asset(path = "player_teleport.png")]
     pub player_teleport: Handle<Image>,
+    #[asset(path = "inverter_pickup.png")]
+    pub inverter_pickup: Handle<Image>,
+    #[asset(path = "hole_pickup.png")]
+    pub hole_pickup: Handle<Image>,
+    #[asset(path = "shield_pickup.png")]
+    pub shield_pickup: Handle<Image>,
+    #[asset(path = "slow_motion_pickup.png")]
+    pub slow_motion_pickup: Handle<Image>,
+    #[asset(path = "magnet_pickup.png")]
+    pub magnet_pickup: Handle<Image>,
+    #[asset(path = "hole.png")]
+    pub hole: Handle<Image>,
 
     #[asset(path = "chips.png")]
     pub chips: Handle<Image>,
@@ -34,6 +48,18 @@ pub struct GameAssets {
     #[asset(path = "menu-image.png")]
     pub menu_image: Handle<Image>,
 
+    #[asset(path = "levels/endless.level.ron")]
+    pub endless_level: Handle<LevelDefinition>,
+    #[asset(
+        paths(
+            "levels/campaign-1.level.ron",
+            "levels/campaign-2.level.ron",
+            "levels/campaign-3.level.ron"
+        ),
+        collection(typed)
+    )]
+    pub campaign: Vec<Handle<LevelDefinition>>,
+
     #[asset(path = "galactic-errands-background.mp3")]
     pub music: Handle<bevy_kira_audio::AudioSource>,
     #[asset(path = "collected.mp3")]
@@ -1,7 +1,30 @@
 use bevy::prelude::*;
+use bevy_kira_audio::{AudioChannel, AudioControl};
+
+use crate::{assets::GameAssets, gravity::CrashEvent, level::LevelEvent, pickup::PickupType};
 
 #[derive(Resource)]
 pub struct BackgroundMusic;
 
 #[derive(Resource)]
 pub struct ForegroundAudio;
+
+pub fn play_event_audio(
+    mut level_events: EventReader<LevelEvent>,
+    mut crashes: EventReader<CrashEvent>,
+    audio: Res<AudioChannel<ForegroundAudio>>,
+    assets: Res<GameAssets>,
+) {
+    for event in level_events.iter() {
+        if let LevelEvent::PickupCollected(pickup, _) = event {
+            if *pickup == PickupType::Goal {
+                audio.play(assets.collected_audio.clone());
+            } else {
+                audio.play(assets.pickup_audio.clone());
+            }
+        }
+    }
+    for _ in crashes.iter() {
+        audio.play(assets.destroyed_audio.clone());
+    }
+}
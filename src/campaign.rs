@@ -0,0 +1,73 @@
+use bevy::prelude::*;
+use iyes_loopless::state::NextState;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::GameAssets,
+    game_state::GameState,
+    level::GoalStatus,
+    level_definition::{CurrentLevel, GoalSequence},
+    storage,
+};
+
+const PROGRESS_KEY: &str = "campaign-progress";
+
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GameMode {
+    #[default]
+    Endless,
+    Campaign,
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct CampaignProgress {
+    /// The level the campaign button will start.
+    pub current: usize,
+    /// The furthest level the player has reached.
+    pub unlocked: usize,
+    #[serde(default)]
+    pub finished: bool,
+}
+
+impl CampaignProgress {
+    pub fn load() -> Self {
+        storage::load(PROGRESS_KEY)
+    }
+
+    pub fn save(&self) {
+        storage::save(PROGRESS_KEY, self);
+    }
+}
+
+pub(crate) fn check_level_complete(
+    mut commands: Commands,
+    goal_status: Res<GoalStatus>,
+    level: Res<CurrentLevel>,
+) {
+    if !goal_status.is_changed() {
+        return;
+    }
+    if let GoalSequence::ShoppingList(list) = &level.0.goals {
+        if goal_status.completed.len() >= list.len() {
+            commands.insert_resource(NextState(GameState::LevelComplete));
+        }
+    }
+}
+
+pub(crate) fn complete_level(
+    mode: Res<GameMode>,
+    mut progress: ResMut<CampaignProgress>,
+    assets: Res<GameAssets>,
+) {
+    if *mode != GameMode::Campaign {
+        return;
+    }
+    let last = assets.campaign.len().saturating_sub(1);
+    if progress.current >= last {
+        progress.finished = true;
+    } else {
+        progress.current += 1;
+        progress.unlocked = progress.unlocked.max(progress.current);
+    }
+    progress.save();
+}
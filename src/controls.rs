@@ -0,0 +1,318 @@
+use std::{hash::Hash, marker::PhantomData};
+
+use bevy::{ecs::system::SystemParam, prelude::*, sprite::MaterialMesh2dBundle};
+
+use crate::{
+    level::{LevelBoundary, LevelEntity},
+    multiplayer::{player_color, Multiplayer},
+    player::Player,
+};
+
+/// How fast the virtual cursor moves at full tilt, in world units per second.
+pub const CURSOR_SPEED: f32 = 400.;
+const STICK_DEAD_ZONE: f32 = 0.2;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Place,
+    Remove,
+    ThrustUp,
+    ThrustDown,
+    ThrustLeft,
+    ThrustRight,
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+    MenuNext,
+    MenuPrevious,
+    MenuConfirm,
+    /// Uses the pickup in the inventory slot with this index.
+    UseSlot(usize),
+    /// Pauses or resumes the simulation, in debug builds.
+    DebugPause,
+    /// Runs a single tick while the simulation is paused, in debug builds.
+    DebugStep,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// Matches the button on any connected gamepad.
+    Button(GamepadButtonType),
+}
+
+/// Which keys and buttons trigger each action, and which sticks steer thrust
+/// and the virtual cursor. Replace the resource to rebind the controls.
+#[derive(Resource, Clone, Debug)]
+pub struct InputMap {
+    pub bindings: Vec<(Action, Binding)>,
+    pub thrust_stick: (GamepadAxisType, GamepadAxisType),
+    pub cursor_stick: (GamepadAxisType, GamepadAxisType),
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use Action::*;
+        use Binding::*;
+        Self {
+            bindings: vec![
+                (Place, Mouse(MouseButton::Left)),
+                (Place, Key(KeyCode::Space)),
+                (Place, Button(GamepadButtonType::South)),
+                (Remove, Mouse(MouseButton::Right)),
+                (Remove, Key(KeyCode::Back)),
+                (Remove, Key(KeyCode::Delete)),
+                (Remove, Button(GamepadButtonType::West)),
+                (ThrustUp, Key(KeyCode::W)),
+                (ThrustDown, Key(KeyCode::S)),
+                (ThrustLeft, Key(KeyCode::A)),
+                (ThrustRight, Key(KeyCode::D)),
+                (CursorUp, Key(KeyCode::Up)),
+                (CursorDown, Key(KeyCode::Down)),
+                (CursorLeft, Key(KeyCode::Left)),
+                (CursorRight, Key(KeyCode::Right)),
+                (MenuNext, Key(KeyCode::Down)),
+                (MenuNext, Key(KeyCode::Tab)),
+                (MenuNext, Button(GamepadButtonType::DPadDown)),
+                (MenuNext, Button(GamepadButtonType::DPadRight)),
+                (MenuPrevious, Key(KeyCode::Up)),
+                (MenuPrevious, Button(GamepadButtonType::DPadUp)),
+                (MenuPrevious, Button(GamepadButtonType::DPadLeft)),
+                (MenuConfirm, Key(KeyCode::Return)),
+                (MenuConfirm, Key(KeyCode::Space)),
+                (MenuConfirm, Button(GamepadButtonType::South)),
+                (UseSlot(0), Key(KeyCode::Key1)),
+                (UseSlot(1), Key(KeyCode::Key2)),
+                (UseSlot(2), Key(KeyCode::Key3)),
+                (UseSlot(0), Button(GamepadButtonType::North)),
+                (UseSlot(1), Button(GamepadButtonType::East)),
+                (UseSlot(2), Button(GamepadButtonType::RightTrigger)),
+                (DebugPause, Key(KeyCode::F9)),
+                (DebugStep, Key(KeyCode::F10)),
+            ],
+            thrust_stick: (GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+            cursor_stick: (GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
+        }
+    }
+}
+
+/// Reads actions from the keyboard, mouse and every connected gamepad through the `InputMap`.
+#[derive(SystemParam)]
+pub struct Controls<'w, 's> {
+    map: Res<'w, InputMap>,
+    keys: Res<'w, Input<KeyCode>>,
+    mouse: Res<'w, Input<MouseButton>>,
+    buttons: Res<'w, Input<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    gamepads: Res<'w, Gamepads>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+#[derive(Clone, Copy)]
+enum Trigger {
+    Pressed,
+    JustPressed,
+    JustReleased,
+}
+
+impl Trigger {
+    fn check<T: Copy + Eq + Hash + Send + Sync + 'static>(
+        self,
+        input: &Input<T>,
+        button: T,
+    ) -> bool {
+        match self {
+            Trigger::Pressed => input.pressed(button),
+            Trigger::JustPressed => input.just_pressed(button),
+            Trigger::JustReleased => input.just_released(button),
+        }
+    }
+}
+
+impl<'w, 's> Controls<'w, 's> {
+    /// Every device at once, for menus that anyone can drive.
+    pub fn anyone(&self) -> PlayerControls<'_, 'w, 's> {
+        PlayerControls {
+            controls: self,
+            keyboard: true,
+            gamepads: self.gamepads.iter().collect(),
+        }
+    }
+
+    /// The devices that belong to one of `players`. The first player has the
+    /// keyboard, mouse and touch screen, and each gamepad goes to the next
+    /// player in turn. Playing alone, every gamepad works too.
+    pub fn player(&self, player: usize, players: usize) -> PlayerControls<'_, 'w, 's> {
+        if players <= 1 {
+            return self.anyone();
+        }
+        let mut gamepads = self.gamepads.iter().collect::<Vec<_>>();
+        gamepads.sort_by_key(|gamepad| gamepad.id);
+        PlayerControls {
+            controls: self,
+            keyboard: player == 0,
+            gamepads: player
+                .checked_sub(1)
+                .and_then(|index| gamepads.get(index))
+                .copied()
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+/// The actions of one player, or of anyone, through the devices they're using.
+pub struct PlayerControls<'a, 'w, 's> {
+    controls: &'a Controls<'w, 's>,
+    keyboard: bool,
+    gamepads: Vec<Gamepad>,
+}
+
+impl<'a, 'w, 's> PlayerControls<'a, 'w, 's> {
+    fn triggered(&self, action: Action, trigger: Trigger) -> bool {
+        let controls = self.controls;
+        controls
+            .map
+            .bindings
+            .iter()
+            .filter(|(bound, _)| *bound == action)
+            .any(|(_, binding)| match *binding {
+                Binding::Key(key) => self.keyboard && trigger.check(&controls.keys, key),
+                Binding::Mouse(button) => self.keyboard && trigger.check(&controls.mouse, button),
+                Binding::Button(button_type) => self.gamepads.iter().any(|gamepad| {
+                    trigger.check(&controls.buttons, GamepadButton::new(*gamepad, button_type))
+                }),
+            })
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.triggered(action, Trigger::Pressed)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.triggered(action, Trigger::JustPressed)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.triggered(action, Trigger::JustReleased)
+    }
+
+    /// The direction held on the four actions and the stick, with a length of at most one.
+    pub fn direction(
+        &self,
+        [up, down, left, right]: [Action; 4],
+        (stick_x, stick_y): (GamepadAxisType, GamepadAxisType),
+    ) -> Vec2 {
+        let axis = |negative, positive| {
+            self.pressed(positive) as i32 as f32 - self.pressed(negative) as i32 as f32
+        };
+        let mut direction = Vec2::new(axis(left, right), axis(down, up));
+
+        for gamepad in self.gamepads.iter() {
+            let axes = &self.controls.axes;
+            let stick = Vec2::new(
+                axes.get(GamepadAxis::new(*gamepad, stick_x))
+                    .unwrap_or_default(),
+                axes.get(GamepadAxis::new(*gamepad, stick_y))
+                    .unwrap_or_default(),
+            );
+            if stick.length() > STICK_DEAD_ZONE {
+                direction += stick;
+            }
+        }
+
+        direction.clamp_length_max(1.)
+    }
+
+    pub fn thrust(&self) -> Vec2 {
+        use Action::*;
+        self.direction(
+            [ThrustUp, ThrustDown, ThrustLeft, ThrustRight],
+            self.controls.map.thrust_stick,
+        )
+    }
+
+    pub fn cursor(&self) -> Vec2 {
+        use Action::*;
+        self.direction(
+            [CursorUp, CursorDown, CursorLeft, CursorRight],
+            self.controls.map.cursor_stick,
+        )
+    }
+}
+
+/// A cursor steered with the keyboard or a gamepad, used for placing a
+/// player's wells instead of the mouse until the mouse moves again.
+#[derive(Component, Default, Debug, Clone, Copy)]
+pub struct VirtualCursor {
+    pub position: Vec2,
+    pub active: bool,
+}
+
+/// Shows where the virtual cursor of the player with this index is.
+#[derive(Component)]
+pub struct VirtualCursorMarker(pub usize);
+
+pub(crate) fn move_virtual_cursor(
+    controls: Controls,
+    mut mouse_moves: EventReader<CursorMoved>,
+    mut players: Query<(&Player, &mut VirtualCursor)>,
+    multiplayer: Res<Multiplayer>,
+    bounds: Res<LevelBoundary>,
+    time: Res<Time>,
+) {
+    let mouse_moved = mouse_moves.iter().count() > 0;
+    for (player, mut cursor) in players.iter_mut() {
+        if mouse_moved && player.0 == 0 && cursor.active {
+            cursor.active = false;
+        }
+
+        let direction = controls.player(player.0, multiplayer.players).cursor();
+        if direction != Vec2::ZERO {
+            cursor.active = true;
+            cursor.position = (cursor.position + direction * CURSOR_SPEED * time.delta_seconds())
+                .clamp(bounds.min, bounds.max);
+        }
+    }
+}
+
+pub(crate) fn show_virtual_cursor(
+    mut commands: Commands,
+    players: Query<(&Player, &VirtualCursor)>,
+    mut markers: Query<(
+        Entity,
+        &VirtualCursorMarker,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (player, _) in players.iter() {
+        if !markers.iter().any(|(_, marker, ..)| marker.0 == player.0) {
+            commands.spawn((
+                MaterialMesh2dBundle {
+                    mesh: meshes.add(shape::RegularPolygon::new(6., 4).into()).into(),
+                    material: color_materials.add(player_color(player.0).into()),
+                    visibility: Visibility::INVISIBLE,
+                    ..default()
+                },
+                VirtualCursorMarker(player.0),
+                LevelEntity,
+            ));
+        }
+    }
+
+    for (entity, marker, mut transform, mut visibility) in markers.iter_mut() {
+        match players.iter().find(|(player, _)| player.0 == marker.0) {
+            Some((_, cursor)) => {
+                visibility.is_visible = cursor.active;
+                transform.translation = cursor.position.extend(1.);
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
+}
@@ -1,11 +1,11 @@
 use belly::prelude::*;
 use bevy::prelude::*;
 
-use crate::game_state::*;
-use iyes_loopless::prelude::*;
+use crate::menu_navigation::{to_menu, MenuFocus};
 
 #[allow(clippy::clone_on_copy)]
 pub fn setup_credits(mut commands: Commands) {
+    commands.insert_resource(MenuFocus::new(vec![("focus-menu", to_menu)]));
     commands.add(eml! {
         <body>
         <div c:menu_image>
@@ -44,7 +44,7 @@ pub fn setup_credits(mut commands: Commands) {
                         "Bevy Kira Audio - https://github.com/NiklasEi/bevy_kira_audio"
                     </div>
                     <div c:buttons>
-                        <button on:press=connect!(|ctx| ctx.commands().insert_resource(NextState(GameState::Menu)))>
+                        <button c:focus-menu on:press=connect!(|ctx| to_menu(ctx.commands()))>
                             <span c:content>
                                 <img src="credits.png"/>
                             </span>
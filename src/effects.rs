@@ -0,0 +1,585 @@
+use std::{borrow::Borrow, sync::Arc};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    assets::GameAssets,
+    gravity::{BlackHole, GravitationalBody, FIXED_TIME_DELTA},
+    gravity_spawner::PendingPlacements,
+    inventory::PendingActivations,
+    pickup::PickupType,
+    replay::ReplayMode,
+    sweep::Contact,
+    time_scale::TimeScale,
+};
+
+pub mod black_hole;
+pub mod inverter;
+pub mod magnet;
+pub mod planet_killer;
+pub mod shield;
+pub mod slow_motion;
+pub mod teleport;
+
+/// Names a pickup effect, which is what level files, replays and saved runs
+/// refer to it by. Each effect declares its name as a constant, and the
+/// registry hands out a shared copy of the name for every effect it knows.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EffectKind(Arc<str>);
+
+impl EffectKind {
+    /// The kind with this name, whether or not an effect has been registered for it.
+    pub fn new(name: &str) -> Self {
+        Self(Arc::from(name))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for EffectKind {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+/// What happens when a pickup is used again while its effect is still on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Stacking {
+    /// The effect starts over with its full duration and charges.
+    #[default]
+    Refresh,
+    /// The new duration and charges are added to what's left.
+    Extend,
+    /// Each use runs as its own effect, alongside the others.
+    Separate,
+}
+
+/// A place to build a well, which the player's effects can change before it's built.
+#[derive(Clone, Copy, Debug)]
+pub struct WellPlacement {
+    pub body: GravitationalBody,
+    pub black_hole: Option<BlackHole>,
+    /// The player is moved to the spot instead of a well being built there.
+    pub teleports: bool,
+}
+
+/// A player flying into a body. An effect that saves them marks it `survived`,
+/// and may move them or get rid of the body.
+#[derive(Clone, Copy, Debug)]
+pub struct CrashResponse {
+    pub contact: Contact,
+    pub black_hole: bool,
+    /// Where the player is at the end of the tick, and how fast they're going.
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub destroy_body: bool,
+    pub survived: bool,
+}
+
+/// A player flying out of the level boundary.
+#[derive(Clone, Copy, Debug)]
+pub struct BoundaryResponse {
+    pub min: Vec2,
+    pub max: Vec2,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub survived: bool,
+}
+
+pub type AssetPicker = fn(&GameAssets) -> Handle<Image>;
+
+/// How a pickup behaves once it's been put to use. Each effect registers its
+/// own rules with `AppEffectExt::register_effect`, and the game's systems go
+/// through them rather than checking for particular pickups.
+#[derive(Clone, Copy, Default)]
+pub struct EffectRules {
+    /// Seconds until the effect wears off, if it's timed.
+    pub duration: Option<f32>,
+    /// How many times the effect can step in before it's spent.
+    pub charges: Option<u32>,
+    pub stacking: Stacking,
+    /// The player is held where they are, out of reach of gravity and thrust,
+    /// while the effect is on.
+    pub holds_player: bool,
+    /// How fast the world runs while the effect is on, as a fraction of normal speed.
+    pub time_scale: Option<f32>,
+    /// How much further than usual the player can collect pickups from.
+    pub collection_reach: Option<f32>,
+    /// Changes the next well the player places, using up a charge.
+    pub on_place: Option<fn(&mut WellPlacement)>,
+    /// Gets a chance to save the player from a crash, using up a charge if it does.
+    pub on_crash: Option<fn(&mut CrashResponse)>,
+    /// Gets a chance to save the player from leaving the level, using up a charge if it does.
+    pub on_boundary: Option<fn(&mut BoundaryResponse)>,
+    /// The image shown for the pickup in the inventory.
+    pub icon: &'static str,
+    pub pickup_image: Option<AssetPicker>,
+    pub pickup_tint: Option<Color>,
+    pub player_image: Option<AssetPicker>,
+    pub player_tint: Option<Color>,
+    /// The player's ship flashes while the effect is on.
+    pub flashes: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct EffectRegistry(HashMap<EffectKind, EffectRules>);
+
+impl EffectRegistry {
+    pub fn get(&self, kind: &str) -> Option<&EffectRules> {
+        self.0.get(kind)
+    }
+
+    /// The registered kind with this name.
+    pub fn kind(&self, name: &str) -> Option<EffectKind> {
+        self.0.get_key_value(name).map(|(kind, _)| kind.clone())
+    }
+
+    pub fn register(&mut self, name: &str, rules: EffectRules) -> EffectKind {
+        let kind = self.kind(name).unwrap_or_else(|| EffectKind::new(name));
+        self.0.insert(kind.clone(), rules);
+        kind
+    }
+
+    /// The pickup with this name, as long as it's one the game knows about.
+    pub fn parse(&self, name: &str) -> Option<PickupType> {
+        match PickupType::named(name) {
+            PickupType::Effect(kind) => self.kind(kind.name()).map(PickupType::Effect),
+            pickup => Some(pickup),
+        }
+    }
+}
+
+pub trait AppEffectExt {
+    fn register_effect(&mut self, name: &str, rules: EffectRules) -> &mut Self;
+}
+
+impl AppEffectExt for App {
+    fn register_effect(&mut self, name: &str, rules: EffectRules) -> &mut Self {
+        self.init_resource::<EffectRegistry>();
+        self.world
+            .resource_mut::<EffectRegistry>()
+            .register(name, rules);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Effect {
+    pub kind: EffectKind,
+    /// Fixed ticks until it wears off, if it's timed.
+    pub remaining_ticks: Option<u64>,
+    /// Uses left before it's spent, if it's counted in charges.
+    pub charges: Option<u32>,
+}
+
+/// The effects running on a player, in the order they were started.
+#[derive(Component, Default, Debug, Clone, PartialEq, Eq)]
+pub struct ActiveEffects(pub Vec<Effect>);
+
+impl ActiveEffects {
+    pub fn has(&self, name: &str) -> bool {
+        self.0.iter().any(|effect| effect.kind.name() == name)
+    }
+
+    pub fn start(&mut self, name: &str, registry: &EffectRegistry) {
+        let (kind, rules) = match registry.0.get_key_value(name) {
+            Some((kind, rules)) => (kind.clone(), rules),
+            None => {
+                warn!("Pickup {} has no effect registered", name);
+                return;
+            }
+        };
+        let effect = Effect {
+            kind,
+            remaining_ticks: rules
+                .duration
+                .map(|duration| (duration / FIXED_TIME_DELTA).round() as u64),
+            charges: rules.charges,
+        };
+        let existing = self
+            .0
+            .iter_mut()
+            .find(|existing| existing.kind == effect.kind);
+        match (rules.stacking, existing) {
+            (Stacking::Refresh, Some(existing)) => *existing = effect,
+            (Stacking::Extend, Some(existing)) => {
+                existing.remaining_ticks = add(existing.remaining_ticks, effect.remaining_ticks);
+                existing.charges = add(existing.charges, effect.charges);
+            }
+            _ => self.0.push(effect),
+        }
+    }
+
+    /// Counts down timed effects by one fixed tick, ending any that run out.
+    pub fn tick(&mut self) {
+        for effect in self.0.iter_mut() {
+            if let Some(ticks) = effect.remaining_ticks.as_mut() {
+                *ticks = ticks.saturating_sub(1);
+            }
+        }
+        self.0.retain(|effect| effect.remaining_ticks != Some(0));
+    }
+
+    /// Uses up a charge of the effect at `index`, ending it once they run out.
+    fn use_charge(&mut self, index: usize) {
+        if let Some(charges) = self.0[index].charges.as_mut() {
+            *charges = charges.saturating_sub(1);
+            if *charges == 0 {
+                self.0.remove(index);
+            }
+        }
+    }
+
+    fn rules<'a>(
+        &'a self,
+        registry: &'a EffectRegistry,
+    ) -> impl Iterator<Item = (usize, &'a EffectRules)> + 'a {
+        self.0
+            .iter()
+            .enumerate()
+            .filter_map(|(index, effect)| Some((index, registry.get(effect.kind.name())?)))
+    }
+
+    pub fn holds_player(&self, registry: &EffectRegistry) -> bool {
+        self.rules(registry).any(|(_, rules)| rules.holds_player)
+    }
+
+    /// The slowest speed any of the effects runs the world at.
+    pub fn time_scale(&self, registry: &EffectRegistry) -> Option<f32> {
+        self.rules(registry)
+            .filter_map(|(_, rules)| rules.time_scale)
+            .reduce(f32::min)
+    }
+
+    /// The furthest any of the effects lets the player reach for pickups.
+    pub fn collection_reach(&self, registry: &EffectRegistry) -> f32 {
+        self.rules(registry)
+            .filter_map(|(_, rules)| rules.collection_reach)
+            .fold(0., f32::max)
+    }
+
+    /// Lets every effect that changes placements have its say, using up a
+    /// charge of each one that does. Returns the newest of them, if any.
+    pub fn place(
+        &mut self,
+        registry: &EffectRegistry,
+        placement: &mut WellPlacement,
+    ) -> Option<EffectKind> {
+        let used = self
+            .rules(registry)
+            .filter_map(|(index, rules)| Some((index, rules.on_place?)))
+            .collect::<Vec<_>>();
+        for (_, on_place) in used.iter() {
+            on_place(placement);
+        }
+        let newest = used.last().map(|(index, _)| self.0[*index].kind.clone());
+        // Charges are used from the back, so ending an effect doesn't move the others.
+        for (index, _) in used.into_iter().rev() {
+            self.use_charge(index);
+        }
+        newest
+    }
+
+    /// Gives each effect a chance to save the player from a crash, oldest first.
+    pub fn crash(&mut self, registry: &EffectRegistry, response: &mut CrashResponse) {
+        let saved_by = self
+            .rules(registry)
+            .filter_map(|(index, rules)| Some((index, rules.on_crash?)))
+            .find(|(_, on_crash)| {
+                on_crash(response);
+                response.survived
+            });
+        if let Some((index, _)) = saved_by {
+            self.use_charge(index);
+        }
+    }
+
+    /// Gives each effect a chance to keep the player in the level, oldest first.
+    pub fn leave_boundary(&mut self, registry: &EffectRegistry, response: &mut BoundaryResponse) {
+        let saved_by = self
+            .rules(registry)
+            .filter_map(|(index, rules)| Some((index, rules.on_boundary?)))
+            .find(|(_, on_boundary)| {
+                on_boundary(response);
+                response.survived
+            });
+        if let Some((index, _)) = saved_by {
+            self.use_charge(index);
+        }
+    }
+
+    /// The newest effect that sets each part of how the player looks.
+    pub fn player_image(&self, registry: &EffectRegistry) -> Option<AssetPicker> {
+        self.rules(registry)
+            .filter_map(|(_, r)| r.player_image)
+            .last()
+    }
+
+    pub fn player_tint(&self, registry: &EffectRegistry) -> Option<Color> {
+        self.rules(registry)
+            .filter_map(|(_, r)| r.player_tint)
+            .last()
+    }
+
+    pub fn flashes(&self, registry: &EffectRegistry) -> bool {
+        self.rules(registry).any(|(_, rules)| rules.flashes)
+    }
+}
+
+fn add<T: std::ops::Add<Output = T>>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        (a, b) => a.or(b),
+    }
+}
+
+/// The framework every pickup effect is built on, along with the effects themselves.
+/// Effects may add their own fixed timestep systems, so it goes in after the timestep.
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EffectRegistry>()
+            .add_plugin(planet_killer::PlanetKillerPlugin)
+            .add_plugin(teleport::TeleportPlugin)
+            .add_plugin(inverter::InverterPlugin)
+            .add_plugin(black_hole::BlackHolePlugin)
+            .add_plugin(shield::ShieldPlugin)
+            .add_plugin(slow_motion::SlowMotionPlugin)
+            .add_plugin(magnet::MagnetPlugin);
+    }
+}
+
+pub(crate) fn tick_effects(mut players: Query<&mut ActiveEffects>) {
+    for mut effects in players.iter_mut() {
+        if effects
+            .0
+            .iter()
+            .any(|effect| effect.remaining_ticks.is_some())
+        {
+            effects.tick();
+        }
+    }
+}
+
+/// Runs the world at the slowest speed any player's effects ask for, and holds
+/// it still while every player is held. A held world lets a tick through once
+/// there's a placement or pickup waiting for it, so the player can be let go.
+/// Replays feed those in during the tick itself, so they're never held.
+pub(crate) fn scale_time(
+    players: Query<&ActiveEffects>,
+    registry: Res<EffectRegistry>,
+    placements: Res<PendingPlacements>,
+    activations: Res<PendingActivations>,
+    replay_mode: Res<ReplayMode>,
+    mut time_scale: ResMut<TimeScale>,
+) {
+    let scale = players
+        .iter()
+        .filter_map(|effects| effects.time_scale(&registry))
+        .fold(1., f32::min);
+    let held = !players.is_empty()
+        && players
+            .iter()
+            .all(|effects| effects.holds_player(&registry))
+        && placements.0.is_empty()
+        && activations.0.is_empty()
+        && matches!(*replay_mode, ReplayMode::Live);
+    if time_scale.scale != scale {
+        time_scale.scale = scale;
+    }
+    if time_scale.held != held {
+        time_scale.held = held;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMED: &str = "Timed";
+    const EXTENDED: &str = "Extended";
+    const SEPARATE: &str = "Separate";
+    const INVERTING: &str = "Inverting";
+    const SAVING: &str = "Saving";
+
+    fn registry() -> EffectRegistry {
+        let timed = EffectRules {
+            duration: Some(FIXED_TIME_DELTA * 3.),
+            ..default()
+        };
+        let mut registry = EffectRegistry::default();
+        registry.register(TIMED, timed);
+        registry.register(
+            EXTENDED,
+            EffectRules {
+                charges: Some(1),
+                stacking: Stacking::Extend,
+                ..timed
+            },
+        );
+        registry.register(
+            SEPARATE,
+            EffectRules {
+                stacking: Stacking::Separate,
+                ..timed
+            },
+        );
+        registry.register(
+            INVERTING,
+            EffectRules {
+                charges: Some(2),
+                on_place: Some(|placement| placement.body.0 = -placement.body.0),
+                ..default()
+            },
+        );
+        registry.register(
+            SAVING,
+            EffectRules {
+                charges: Some(1),
+                on_crash: Some(|crash| crash.survived = !crash.black_hole),
+                ..default()
+            },
+        );
+        registry
+    }
+
+    fn remaining_ticks(effects: &ActiveEffects) -> Vec<Option<u64>> {
+        effects
+            .0
+            .iter()
+            .map(|effect| effect.remaining_ticks)
+            .collect()
+    }
+
+    fn crash(black_hole: bool) -> CrashResponse {
+        CrashResponse {
+            contact: Contact {
+                point: Vec2::new(10., 0.),
+                time: 0.5,
+                normal: Vec2::X,
+            },
+            black_hole,
+            position: Vec2::new(5., 0.),
+            velocity: Vec2::new(-20., 0.),
+            destroy_body: false,
+            survived: false,
+        }
+    }
+
+    #[test]
+    fn timed_effects_end_when_they_run_out() {
+        let registry = registry();
+        let mut effects = ActiveEffects::default();
+        effects.start(TIMED, &registry);
+
+        effects.tick();
+        effects.tick();
+        assert_eq!(remaining_ticks(&effects), vec![Some(1)]);
+
+        effects.tick();
+        assert!(!effects.has(TIMED));
+    }
+
+    #[test]
+    fn refreshing_starts_the_effect_over() {
+        let registry = registry();
+        let mut effects = ActiveEffects::default();
+        effects.start(TIMED, &registry);
+        effects.tick();
+        effects.tick();
+
+        effects.start(TIMED, &registry);
+
+        assert_eq!(remaining_ticks(&effects), vec![Some(3)]);
+    }
+
+    #[test]
+    fn extending_adds_to_what_is_left() {
+        let registry = registry();
+        let mut effects = ActiveEffects::default();
+        effects.start(EXTENDED, &registry);
+        effects.tick();
+
+        effects.start(EXTENDED, &registry);
+
+        assert_eq!(remaining_ticks(&effects), vec![Some(5)]);
+        assert_eq!(effects.0[0].charges, Some(2));
+    }
+
+    #[test]
+    fn separate_uses_run_alongside_each_other() {
+        let registry = registry();
+        let mut effects = ActiveEffects::default();
+        effects.start(SEPARATE, &registry);
+        effects.tick();
+        effects.start(SEPARATE, &registry);
+        assert_eq!(remaining_ticks(&effects), vec![Some(2), Some(3)]);
+
+        effects.tick();
+        effects.tick();
+        assert_eq!(remaining_ticks(&effects), vec![Some(1)]);
+
+        effects.tick();
+        assert!(effects.0.is_empty());
+    }
+
+    #[test]
+    fn placing_uses_up_a_charge() {
+        let registry = registry();
+        let mut effects = ActiveEffects::default();
+        effects.start(INVERTING, &registry);
+        let mut placement = WellPlacement {
+            body: GravitationalBody(100., 10.),
+            black_hole: None,
+            teleports: false,
+        };
+
+        let used = effects.place(&registry, &mut placement);
+        assert_eq!(used, Some(EffectKind::new(INVERTING)));
+        assert_eq!(placement.body.0, -100.);
+        assert_eq!(effects.0[0].charges, Some(1));
+
+        effects.place(&registry, &mut placement);
+        assert_eq!(placement.body.0, 100.);
+        assert!(!effects.has(INVERTING));
+        assert_eq!(effects.place(&registry, &mut placement), None);
+    }
+
+    #[test]
+    fn only_the_effect_that_saves_a_crash_is_used_up() {
+        let registry = registry();
+        let mut effects = ActiveEffects::default();
+        effects.start(TIMED, &registry);
+        effects.start(SAVING, &registry);
+
+        let mut fatal = crash(true);
+        effects.crash(&registry, &mut fatal);
+        assert!(!fatal.survived);
+        assert!(effects.has(SAVING));
+
+        let mut saved = crash(false);
+        effects.crash(&registry, &mut saved);
+        assert!(saved.survived);
+        assert!(!effects.has(SAVING));
+        assert!(effects.has(TIMED));
+    }
+
+    #[test]
+    fn unregistered_effects_are_not_started_or_parsed() {
+        let registry = registry();
+        let mut effects = ActiveEffects::default();
+
+        effects.start("Unknown", &registry);
+
+        assert!(effects.0.is_empty());
+        assert_eq!(registry.parse("Unknown"), None);
+        assert_eq!(
+            registry.parse("Timed"),
+            Some(PickupType::Effect(EffectKind::new(TIMED)))
+        );
+        assert_eq!(registry.parse("Fuel"), Some(PickupType::Fuel));
+    }
+}
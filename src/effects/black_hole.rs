@@ -0,0 +1,29 @@
+use bevy::prelude::*;
+
+use super::{AppEffectExt, EffectRules, WellPlacement};
+use crate::gravity::BlackHole;
+
+pub const BLACK_HOLE: &str = "BlackHole";
+
+/// Turns the next well into a black hole.
+pub struct BlackHolePlugin;
+
+impl Plugin for BlackHolePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_effect(
+            BLACK_HOLE,
+            EffectRules {
+                icon: "hole_pickup.png",
+                charges: Some(1),
+                on_place: Some(black_hole),
+                pickup_image: Some(|assets| assets.hole_pickup.clone()),
+                ..default()
+            },
+        );
+    }
+}
+
+fn black_hole(placement: &mut WellPlacement) {
+    placement.body = BlackHole::body();
+    placement.black_hole = Some(BlackHole::default());
+}
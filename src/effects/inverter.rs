@@ -0,0 +1,29 @@
+use bevy::prelude::*;
+
+use super::{AppEffectExt, EffectRules, WellPlacement};
+use crate::player::INVERTER_TINT;
+
+pub const INVERTER: &str = "Inverter";
+
+/// Turns the next well into one that pushes the player away.
+pub struct InverterPlugin;
+
+impl Plugin for InverterPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_effect(
+            INVERTER,
+            EffectRules {
+                icon: "inverter_pickup.png",
+                charges: Some(1),
+                on_place: Some(invert),
+                pickup_image: Some(|assets| assets.inverter_pickup.clone()),
+                player_tint: Some(INVERTER_TINT),
+                ..default()
+            },
+        );
+    }
+}
+
+fn invert(placement: &mut WellPlacement) {
+    placement.body.0 = -placement.body.0.abs();
+}
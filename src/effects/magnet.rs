@@ -0,0 +1,148 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use iyes_loopless::prelude::{AppLooplessFixedTimestepExt, ConditionSet};
+
+use super::{ActiveEffects, AppEffectExt, EffectRules};
+use crate::{
+    game_state::GameState,
+    gravity::{GravitationTransform, FIXED_TIME_DELTA},
+    level::{GoalOwner, LevelEntity},
+    pickup::Pickup,
+    player::Player,
+    simulation::physics_tick_set,
+};
+
+pub const MAGNET: &str = "Magnet";
+pub const MAGNET_TINT: Color = Color::rgb(1., 0.5, 0.5);
+pub const MAGNET_DURATION: f32 = 8.;
+/// How far away pickups start drifting towards the player.
+pub const MAGNET_RANGE: f32 = 200.;
+/// How fast pulled pickups drift, in world units per second.
+pub const MAGNET_PULL_SPEED: f32 = 120.;
+/// How much further than usual the player can reach to collect a pickup.
+pub const MAGNET_REACH: f32 = 20.;
+const RANGE_RING_DOTS: usize = 32;
+
+/// Draws pickups in from around the player for a while, and lets them be
+/// collected from further away.
+pub struct MagnetPlugin;
+
+impl Plugin for MagnetPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_effect(
+            MAGNET,
+            EffectRules {
+                icon: "magnet_pickup.png",
+                duration: Some(MAGNET_DURATION),
+                collection_reach: Some(MAGNET_REACH),
+                pickup_image: Some(|assets| assets.magnet_pickup.clone()),
+                player_tint: Some(MAGNET_TINT),
+                ..default()
+            },
+        )
+        .add_fixed_timestep_system_set(
+            "calculate_physics",
+            0,
+            physics_tick_set()
+                .after("activations")
+                .with_system(pull_pickups)
+                .into(),
+        )
+        // The range ring is drawn with meshes, which headless runs don't have.
+        .add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Playing)
+                .run_if_resource_exists::<Assets<ColorMaterial>>()
+                .with_system(show_magnet_range)
+                .into(),
+        );
+    }
+}
+
+type PickupQueryConditions = (With<Pickup>, Without<Player>);
+
+/// Moves each pickup in range a step towards the nearest player with a magnet.
+/// Goals meant for someone else stay put.
+fn pull_pickups(
+    players: Query<(&Player, &Transform, &GravitationTransform, &ActiveEffects)>,
+    mut pickups: Query<(&mut Transform, Option<&GoalOwner>), PickupQueryConditions>,
+) {
+    let magnets = players
+        .iter()
+        .filter(|(.., effects)| effects.has(MAGNET))
+        .map(|(player, transform, gravitation_transform, _)| {
+            (
+                player.0,
+                gravitation_transform.simulated_position(transform),
+            )
+        })
+        .collect::<Vec<_>>();
+    if magnets.is_empty() {
+        return;
+    }
+
+    for (mut transform, owner) in pickups.iter_mut() {
+        let position = transform.translation.truncate();
+        let closest = magnets
+            .iter()
+            .filter(|(player, _)| !matches!(owner, Some(owner) if owner.0 != *player))
+            .map(|(_, magnet)| (*magnet, magnet.distance(position)))
+            .filter(|(_, distance)| *distance <= MAGNET_RANGE)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((magnet, distance)) = closest {
+            let step = (MAGNET_PULL_SPEED * FIXED_TIME_DELTA).min(distance);
+            let moved = position + (magnet - position).normalize_or_zero() * step;
+            transform.translation = moved.extend(transform.translation.z);
+        }
+    }
+}
+
+/// A ring of dots around the player showing how far their magnet reaches.
+#[derive(Component)]
+pub struct MagnetRing(pub usize);
+
+fn show_magnet_range(
+    mut commands: Commands,
+    players: Query<(&Player, &Transform, &ActiveEffects), Without<MagnetRing>>,
+    mut rings: Query<(Entity, &MagnetRing, &mut Transform, &mut Visibility)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (player, ..) in players.iter() {
+        if rings.iter().any(|(_, ring, ..)| ring.0 == player.0) {
+            continue;
+        }
+        let mesh = meshes.add(shape::RegularPolygon::new(2., 4).into());
+        let material = color_materials.add(MAGNET_TINT.into());
+        commands
+            .spawn((
+                SpatialBundle {
+                    visibility: Visibility::INVISIBLE,
+                    ..default()
+                },
+                MagnetRing(player.0),
+                LevelEntity,
+            ))
+            .with_children(|ring| {
+                for dot in 0..RANGE_RING_DOTS {
+                    let angle = dot as f32 / RANGE_RING_DOTS as f32 * std::f32::consts::TAU;
+                    let offset = Vec2::from_angle(angle) * MAGNET_RANGE;
+                    ring.spawn(MaterialMesh2dBundle {
+                        mesh: mesh.clone().into(),
+                        material: material.clone(),
+                        transform: Transform::from_translation(offset.extend(0.)),
+                        ..default()
+                    });
+                }
+            });
+    }
+
+    for (entity, ring, mut transform, mut visibility) in rings.iter_mut() {
+        match players.iter().find(|(player, ..)| player.0 == ring.0) {
+            Some((_, player_transform, effects)) => {
+                visibility.is_visible = effects.has(MAGNET);
+                transform.translation = player_transform.translation.truncate().extend(1.);
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
+}
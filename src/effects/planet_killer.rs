@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+
+use super::{AppEffectExt, CrashResponse, EffectRules};
+
+pub const PLANET_KILLER: &str = "PlanetKiller";
+
+/// Smashes through the next planet the player flies into. Black holes are too
+/// much for it.
+pub struct PlanetKillerPlugin;
+
+impl Plugin for PlanetKillerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_effect(
+            PLANET_KILLER,
+            EffectRules {
+                icon: "planet_killer_pickup.png",
+                charges: Some(1),
+                on_crash: Some(destroy_planet),
+                pickup_image: Some(|assets| assets.planet_killer_pickup.clone()),
+                player_image: Some(|assets| assets.player_planet_killer.clone()),
+                flashes: true,
+                ..default()
+            },
+        );
+    }
+}
+
+fn destroy_planet(crash: &mut CrashResponse) {
+    if !crash.black_hole {
+        crash.destroy_body = true;
+        crash.survived = true;
+    }
+}
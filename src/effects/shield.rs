@@ -0,0 +1,109 @@
+use bevy::prelude::*;
+
+use super::{AppEffectExt, BoundaryResponse, CrashResponse, EffectRules};
+
+pub const SHIELD: &str = "Shield";
+pub const SHIELD_TINT: Color = Color::rgb(0.5, 0.8, 1.);
+
+/// How far off the surface a bounced player is put, so they don't touch it
+/// again on the next tick.
+const SHIELD_CLEARANCE: f32 = 1.;
+
+/// Bounces the player off the next planet they fly into, or back in from the
+/// edge of the level, whichever comes first. Black holes still swallow them.
+pub struct ShieldPlugin;
+
+impl Plugin for ShieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_effect(
+            SHIELD,
+            EffectRules {
+                icon: "shield_pickup.png",
+                charges: Some(1),
+                on_crash: Some(bounce_off_body),
+                on_boundary: Some(bounce_off_boundary),
+                pickup_image: Some(|assets| assets.shield_pickup.clone()),
+                player_tint: Some(SHIELD_TINT),
+                ..default()
+            },
+        );
+    }
+}
+
+fn bounce_off_body(crash: &mut CrashResponse) {
+    // Nothing bounces back out of a black hole.
+    if crash.black_hole {
+        return;
+    }
+    let normal = crash.contact.normal;
+    let towards = crash.velocity.dot(normal);
+    if towards < 0. {
+        crash.velocity -= 2. * towards * normal;
+    }
+    crash.position = crash.contact.point + normal * SHIELD_CLEARANCE;
+    crash.survived = true;
+}
+
+fn bounce_off_boundary(exit: &mut BoundaryResponse) {
+    let mut position = exit.position;
+    let mut velocity = exit.velocity;
+    if position.x < exit.min.x {
+        position.x = 2. * exit.min.x - position.x;
+        velocity.x = velocity.x.abs();
+    } else if position.x > exit.max.x {
+        position.x = 2. * exit.max.x - position.x;
+        velocity.x = -velocity.x.abs();
+    }
+    if position.y < exit.min.y {
+        position.y = 2. * exit.min.y - position.y;
+        velocity.y = velocity.y.abs();
+    } else if position.y > exit.max.y {
+        position.y = 2. * exit.max.y - position.y;
+        velocity.y = -velocity.y.abs();
+    }
+    exit.position = position.clamp(exit.min, exit.max);
+    exit.velocity = velocity;
+    exit.survived = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sweep::Contact;
+
+    fn crash(black_hole: bool) -> CrashResponse {
+        CrashResponse {
+            contact: Contact {
+                point: Vec2::new(-10., 0.),
+                time: 0.5,
+                normal: Vec2::NEG_X,
+            },
+            black_hole,
+            position: Vec2::new(-5., 0.),
+            velocity: Vec2::new(20., 5.),
+            destroy_body: false,
+            survived: false,
+        }
+    }
+
+    #[test]
+    fn bounces_off_the_surface() {
+        let mut crash = crash(false);
+
+        bounce_off_body(&mut crash);
+
+        assert!(crash.survived);
+        assert_eq!(crash.velocity, Vec2::new(-20., 5.));
+        assert_eq!(crash.position, Vec2::new(-10. - SHIELD_CLEARANCE, 0.));
+    }
+
+    #[test]
+    fn black_holes_still_swallow_the_player() {
+        let mut crash = crash(true);
+
+        bounce_off_body(&mut crash);
+
+        assert!(!crash.survived);
+        assert_eq!(crash.position, Vec2::new(-5., 0.));
+    }
+}
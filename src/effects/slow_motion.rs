@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+
+use super::{AppEffectExt, EffectRules};
+
+pub const SLOW_MOTION: &str = "SlowMotion";
+pub const SLOW_MOTION_TINT: Color = Color::rgb(0.8, 0.6, 1.);
+pub const SLOW_MOTION_SCALE: f32 = 0.3;
+/// In simulated seconds, so it lasts five seconds of real time at `SLOW_MOTION_SCALE`.
+pub const SLOW_MOTION_DURATION: f32 = 1.5;
+
+/// Slows the whole world down for a while, to give the player time to think.
+pub struct SlowMotionPlugin;
+
+impl Plugin for SlowMotionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_effect(
+            SLOW_MOTION,
+            EffectRules {
+                icon: "slow_motion_pickup.png",
+                duration: Some(SLOW_MOTION_DURATION),
+                time_scale: Some(SLOW_MOTION_SCALE),
+                pickup_image: Some(|assets| assets.slow_motion_pickup.clone()),
+                player_tint: Some(SLOW_MOTION_TINT),
+                ..default()
+            },
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+
+use super::{AppEffectExt, EffectRules, WellPlacement};
+
+pub const TELEPORT: &str = "Teleport";
+
+/// Holds the player still while they pick a spot, then moves them there
+/// instead of placing a well.
+pub struct TeleportPlugin;
+
+impl Plugin for TeleportPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_effect(
+            TELEPORT,
+            EffectRules {
+                icon: "teleport.png",
+                charges: Some(1),
+                holds_player: true,
+                on_place: Some(teleport),
+                pickup_image: Some(|assets| assets.teleport.clone()),
+                player_image: Some(|assets| assets.player_teleport.clone()),
+                ..default()
+            },
+        );
+    }
+}
+
+fn teleport(placement: &mut WellPlacement) {
+    placement.teleports = true;
+    placement.body.0 = 0.;
+}
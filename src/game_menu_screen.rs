@@ -1,11 +1,41 @@
 use belly::prelude::*;
 use bevy::prelude::*;
 
-use crate::game_state::*;
-use iyes_loopless::prelude::*;
+use crate::{
+    campaign::{CampaignProgress, GameMode},
+    gameplay_settings::{cycle_difficulty, GameplaySettings},
+    leaderboard::{Leaderboard, BEST_RUNS_SHOWN},
+    menu_navigation::{play, to_credits, MenuFocus},
+    multiplayer::{cycle_multiplayer, Multiplayer},
+};
+
+/// The labels of the buttons that change a setting, kept up to date as
+/// they're pressed so the menu doesn't have to be rebuilt.
+#[derive(Resource, Default, PartialEq)]
+pub struct MenuLabels {
+    pub difficulty: String,
+    pub players: String,
+}
 
 #[allow(clippy::clone_on_copy)]
-pub fn setup_menu(mut commands: Commands) {
+pub fn setup_menu(
+    mut commands: Commands,
+    progress: Res<CampaignProgress>,
+    leaderboard: Res<Leaderboard>,
+) {
+    let campaign = format!("Level {}", progress.current + 1);
+    let best_runs = leaderboard
+        .best(BEST_RUNS_SHOWN)
+        .iter()
+        .map(|run| run.summary())
+        .collect::<Vec<_>>();
+    commands.insert_resource(MenuFocus::new(vec![
+        ("focus-endless", play_endless),
+        ("focus-campaign", play_campaign),
+        ("focus-difficulty", change_difficulty),
+        ("focus-players", change_players),
+        ("focus-credits", to_credits),
+    ]));
     commands.add(eml! {
         <body>
         <div c:menu_image>
@@ -20,19 +50,71 @@ pub fn setup_menu(mut commands: Commands) {
                         "By Lee-Orr"
                     </div>
                     <div c:buttons>
-                        <button on:press=connect!(|ctx| ctx.commands().insert_resource(NextState(GameState::Playing)))>
+                        <button c:focus-endless on:press=connect!(|ctx| play_endless(ctx.commands()))>
                             <span c:content>
                                 <img src="paper.png"/>
                             </span>
                         </button>
-                        <button c:credits_button on:press=connect!(|ctx| ctx.commands().insert_resource(NextState(GameState::Credits)))>
+                        <button c:credits_button c:focus-campaign on:press=connect!(|ctx| play_campaign(ctx.commands()))>
+                            <span c:content>
+                                {campaign}
+                            </span>
+                        </button>
+                        <button c:credits_button c:focus-difficulty on:press=connect!(|ctx| change_difficulty(ctx.commands()))>
+                            <span c:content>
+                                <label bind:value=from!(MenuLabels:difficulty | fmt.val("{val}"))/>
+                            </span>
+                        </button>
+                        <button c:credits_button c:focus-players on:press=connect!(|ctx| change_players(ctx.commands()))>
+                            <span c:content>
+                                <label bind:value=from!(MenuLabels:players | fmt.val("{val}"))/>
+                            </span>
+                        </button>
+                        <button c:credits_button c:focus-credits on:press=connect!(|ctx| to_credits(ctx.commands()))>
                             <span c:content>
                                 "Credits"
                             </span>
                         </button>
                     </div>
+                    <div c:best_runs>
+                        <for run in = best_runs>
+                            <div>{run}</div>
+                        </for>
+                    </div>
                 </div>
             </div>
         </body>
     });
 }
+
+fn play_endless(commands: &mut Commands) {
+    commands.insert_resource(GameMode::Endless);
+    play(commands);
+}
+
+fn play_campaign(commands: &mut Commands) {
+    commands.insert_resource(GameMode::Campaign);
+    play(commands);
+}
+
+fn change_difficulty(commands: &mut Commands) {
+    commands.add(cycle_difficulty);
+}
+
+fn change_players(commands: &mut Commands) {
+    commands.add(cycle_multiplayer);
+}
+
+pub(crate) fn update_menu_labels(
+    mut labels: ResMut<MenuLabels>,
+    settings: Res<GameplaySettings>,
+    multiplayer: Res<Multiplayer>,
+) {
+    let next = MenuLabels {
+        difficulty: settings.difficulty.name().to_string(),
+        players: multiplayer.name(),
+    };
+    if *labels != next {
+        *labels = next;
+    }
+}
@@ -1,16 +1,59 @@
 use belly::prelude::*;
 use bevy::prelude::*;
 
-use crate::{game_state::*, level::GoalStatus};
-use iyes_loopless::prelude::*;
+use crate::{
+    leaderboard::{Leaderboard, BEST_RUNS_SHOWN},
+    level::{GoalStatus, LevelSeed},
+    menu_navigation::{play, to_menu, MenuFocus},
+    multiplayer::{player_name, Multiplayer},
+    score::Score,
+};
 
 #[allow(clippy::clone_on_copy)]
-pub fn setup_game_over(mut commands: Commands, goals: Res<GoalStatus>) {
+pub fn setup_game_over(
+    mut commands: Commands,
+    goals: Res<GoalStatus>,
+    seed: Res<LevelSeed>,
+    leaderboard: Res<Leaderboard>,
+    score: Res<Score>,
+    multiplayer: Res<Multiplayer>,
+) {
     let goals = goals
         .completed
         .iter()
         .map(|g| g.get_asset_string())
         .collect::<Vec<_>>();
+    let seed = format!("Seed: {}", seed.0);
+    let total = format!("Score: {}", score.points);
+    let standings = if multiplayer.players > 1 {
+        score
+            .standings()
+            .into_iter()
+            .map(|(player, points)| format!("{}: {points}", player_name(player)))
+            .collect::<Vec<_>>()
+    } else {
+        vec![]
+    };
+    let winner = match score.standings().first() {
+        Some((player, _)) if multiplayer.is_competitive() => {
+            format!("Winner: {}", player_name(*player))
+        }
+        _ => String::new(),
+    };
+    let breakdown = score
+        .breakdown
+        .iter()
+        .map(|(kind, points)| format!("{kind}: {points}"))
+        .collect::<Vec<_>>();
+    let best_runs = leaderboard
+        .best(BEST_RUNS_SHOWN)
+        .iter()
+        .map(|run| run.summary())
+        .collect::<Vec<_>>();
+    commands.insert_resource(MenuFocus::new(vec![
+        ("focus-play", play),
+        ("focus-menu", to_menu),
+    ]));
     commands.add(eml! {
         <body>
             <div c:modal>
@@ -20,13 +63,37 @@ pub fn setup_game_over(mut commands: Commands, goals: Res<GoalStatus>) {
                         <img src=goal/>
                     </for>
                 </div>
+                <div c:score>
+                    {total}
+                </div>
+                <div c:score>
+                    {winner}
+                </div>
+                <div c:best_runs>
+                    <for line in = standings>
+                        <div>{line}</div>
+                    </for>
+                </div>
+                <div c:best_runs>
+                    <for line in = breakdown>
+                        <div>{line}</div>
+                    </for>
+                </div>
+                <div c:seed>
+                    {seed}
+                </div>
+                <div c:best_runs>
+                    <for run in = best_runs>
+                        <div>{run}</div>
+                    </for>
+                </div>
                 <div>
-                    <button on:press=connect!(|ctx| ctx.commands().insert_resource(NextState(GameState::Playing)))>
+                    <button c:focus-play on:press=connect!(|ctx| play(ctx.commands()))>
                         <span c:content>
                             <img src="paper.png"/>
                         </span>
                     </button>
-                    <button on:press=connect!(|ctx| ctx.commands().insert_resource(NextState(GameState::Menu)))>
+                    <button c:focus-menu on:press=connect!(|ctx| to_menu(ctx.commands()))>
                         <span c:content>
                             <img src="credits.png"/>
                         </span>
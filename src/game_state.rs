@@ -4,5 +4,6 @@ pub enum GameState {
     Menu,
     Credits,
     Playing,
+    LevelComplete,
     GameOver,
 }
@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+const SETTINGS_KEY: &str = "gameplay-settings";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+}
+
+/// Balance values for a run. They're saved along with the chosen preset, so
+/// the saved file can be edited to try out new values without a rebuild.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct GameplaySettings {
+    pub difficulty: Difficulty,
+    /// The gravitational constant.
+    pub gravity: f32,
+    /// Speed is clamped to this unless a black hole is doing the pulling.
+    pub max_velocity: f32,
+    /// Scales each level's chance of spawning a pickup.
+    pub pickup_chance: f32,
+    /// The clearance between a new goal and any body.
+    pub goal_gap: f32,
+    /// The clearance between a new planet or pickup and any body.
+    pub planet_gap: f32,
+    /// How long a new planet flashes before it starts pulling.
+    pub grace_period: f32,
+    pub planet_mass: f32,
+}
+
+impl Default for GameplaySettings {
+    fn default() -> Self {
+        Self::preset(Difficulty::Normal)
+    }
+}
+
+impl GameplaySettings {
+    pub fn preset(difficulty: Difficulty) -> Self {
+        match difficulty {
+            Difficulty::Easy => Self {
+                difficulty,
+                gravity: 25.,
+                max_velocity: 250.,
+                pickup_chance: 1.5,
+                goal_gap: 150.,
+                planet_gap: 80.,
+                grace_period: 4.,
+                planet_mass: 8000.,
+            },
+            Difficulty::Normal => Self {
+                difficulty,
+                gravity: 30.,
+                max_velocity: 300.,
+                pickup_chance: 1.,
+                goal_gap: 100.,
+                planet_gap: 50.,
+                grace_period: 3.,
+                planet_mass: 10000.,
+            },
+            Difficulty::Hard => Self {
+                difficulty,
+                gravity: 35.,
+                max_velocity: 350.,
+                pickup_chance: 0.6,
+                goal_gap: 80.,
+                planet_gap: 30.,
+                grace_period: 2.,
+                planet_mass: 12000.,
+            },
+        }
+    }
+
+    pub fn load() -> Self {
+        storage::load(SETTINGS_KEY)
+    }
+
+    pub fn save(&self) {
+        storage::save(SETTINGS_KEY, self);
+    }
+}
+
+/// Switches to the next difficulty preset and saves it.
+pub fn cycle_difficulty(world: &mut World) {
+    let mut settings = world.resource_mut::<GameplaySettings>();
+    *settings = GameplaySettings::preset(settings.difficulty.next());
+    settings.save();
+}
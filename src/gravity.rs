@@ -1,22 +1,66 @@
-use bevy::{math::Vec3Swizzles, prelude::*};
-use bevy_kira_audio::{AudioChannel, AudioControl};
-use iyes_loopless::state::NextState;
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
 
 use crate::{
-    assets::GameAssets,
-    audio::ForegroundAudio,
-    game_state::GameState,
-    gravity_spawner::{Deletable, Prediction, TrajectoryPoint},
-    pickup::{ActivePickup, PickupType},
+    effects::{ActiveEffects, BoundaryResponse, CrashResponse, EffectRegistry},
+    gameplay_settings::GameplaySettings,
+    gravity_spawner::{
+        plain_well, well_to_replace, PlacedWell, Prediction, TrajectoryPoint, TrajectorySettings,
+        WellRules,
+    },
+    gravity_world::{GravityWorld, Integration, WorldBody},
+    level::LevelBoundary,
+    multiplayer::{knock_out, Multiplayer},
+    pickup::Pickup,
+    planet_path::PlanetPath,
     player::{self, Player},
+    replay::PhysicsTick,
+    score::{NearMisses, ScoreEvent, ScoreKind, NEAR_MISS_MARGIN, NEAR_MISS_POINTS},
+    spatial_index::SpatialGrid,
+    sweep::{sweep_circle, Contact},
+    thrust::{Fuel, ThrustInput},
+    time_scale::TimeScale,
 };
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy, Debug)]
 pub struct GravitationalBody(pub f32, pub f32);
 
 #[derive(Component)]
 pub struct DelayedActivity(pub f32);
 
+/// A body that crashes anything crossing its event horizon, regardless of its
+/// visual radius, and whose pull isn't limited by `GameplaySettings::max_velocity`.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct BlackHole {
+    pub event_horizon: f32,
+}
+
+pub const BLACK_HOLE_MASS: f32 = 60000.;
+pub const BLACK_HOLE_RADIUS: f32 = 40.;
+pub const BLACK_HOLE_EVENT_HORIZON: f32 = 15.;
+
+impl Default for BlackHole {
+    fn default() -> Self {
+        Self {
+            event_horizon: BLACK_HOLE_EVENT_HORIZON,
+        }
+    }
+}
+
+impl BlackHole {
+    pub fn body() -> GravitationalBody {
+        GravitationalBody(BLACK_HOLE_MASS, BLACK_HOLE_RADIUS)
+    }
+}
+
+pub type BodyQueryItem<'a> = (
+    Entity,
+    &'a Transform,
+    &'a GravitationTransform,
+    &'a GravitationalBody,
+    Option<&'a BlackHole>,
+    Option<&'a PlanetPath>,
+);
+
 #[derive(Component)]
 pub enum GravitationTransform {
     Static,
@@ -25,6 +69,11 @@ pub enum GravitationTransform {
         start_position: Option<Vec2>,
         target_position: Option<Vec2>,
     },
+    /// Moved along a `PlanetPath` rather than by gravity.
+    Kinematic {
+        start_position: Option<Vec2>,
+        target_position: Option<Vec2>,
+    },
 }
 
 impl GravitationTransform {
@@ -35,98 +84,94 @@ impl GravitationTransform {
             target_position: None,
         }
     }
+
+    /// The position as of the latest fixed tick, rather than the interpolated `Transform`.
+    pub fn simulated_position(&self, transform: &Transform) -> Vec2 {
+        match self {
+            Self::Velocity {
+                target_position: Some(target),
+                ..
+            }
+            | Self::Kinematic {
+                target_position: Some(target),
+                ..
+            } => *target,
+            _ => transform.translation.truncate(),
+        }
+    }
+
+    /// Where the body started and ended the latest fixed tick.
+    pub fn last_step(&self, transform: &Transform) -> (Vec2, Vec2) {
+        match self {
+            Self::Velocity {
+                start_position: Some(start),
+                target_position: Some(target),
+                ..
+            }
+            | Self::Kinematic {
+                start_position: Some(start),
+                target_position: Some(target),
+            } => (*start, *target),
+            _ => {
+                let position = transform.translation.truncate();
+                (position, position)
+            }
+        }
+    }
 }
 
-pub(crate) const G: f32 = 30.;
 pub(crate) const FIXED_TIME_FPS: f32 = 15.;
 pub(crate) const FIXED_TIME_DELTA: f32 = 1. / FIXED_TIME_FPS;
 pub(crate) const FIXED_TIME_MILIS: u64 = (FIXED_TIME_DELTA * 1000.) as u64;
 pub const GAP_BETWEEN_TRAJECTORY: f32 = 0.5;
 
-pub const MAX_VELOCITY: f32 = 300.;
-
 pub(crate) fn calculate_gravity(
     mut commands: Commands,
-    query: Query<
-        (
-            Entity,
-            &Transform,
-            &GravitationTransform,
-            &GravitationalBody,
-        ),
-        Without<DelayedActivity>,
-    >,
-    active_pickup: Res<ActivePickup>,
+    query: Query<BodyQueryItem, Without<DelayedActivity>>,
+    players: Query<&ActiveEffects>,
+    tick: Res<PhysicsTick>,
+    integration: Res<Integration>,
+    settings: Res<GameplaySettings>,
+    registry: Res<EffectRegistry>,
 ) {
-    if matches!(active_pickup.0, Some(PickupType::Teleport)) {
-        return;
-    }
-    for (entity, transform, velocity, gravity) in query.iter() {
-        if let GravitationTransform::Velocity {
-            velocity: v,
-            start_position: _,
-            target_position,
-        } = velocity
-        {
-            let position = target_position.unwrap_or(transform.translation.xy());
-            let (velocity, position, translation) =
-                process_gravity_trajectory(v, position, query.iter(), entity, gravity, None);
-
-            commands
-                .entity(entity)
-                .insert(GravitationTransform::Velocity {
-                    velocity,
-                    start_position: Some(position),
-                    target_position: Some(translation),
-                });
-        }
-    }
-}
+    let mut world = GravityWorld::from_bodies(query.iter());
+    world.integration = *integration;
+    world.settings = *settings;
+    // The bodies are still where the previous tick left them.
+    world.time = tick.time() - FIXED_TIME_DELTA;
 
-pub fn process_gravity_trajectory<
-    'a,
-    T: Iterator<
-        Item = (
-            Entity,
-            &'a Transform,
-            &'a GravitationTransform,
-            &'a GravitationalBody,
-        ),
-    >,
->(
-    v: &Vec2,
-    position: Vec2,
-    query: T,
-    entity: Entity,
-    gravity: &GravitationalBody,
-    phantom: Option<(&Vec2, &GravitationalBody)>,
-) -> (Vec2, Vec2, Vec2) {
-    let mut velocity = *v;
-    for (entity_2, t_2, _, g_2) in query {
-        if entity_2 == entity {
-            continue;
-        }
-        let r = t_2.translation.xy() - position;
-        let d_sq = r.length_squared();
-        if d_sq > 30. {
-            velocity += (G * FIXED_TIME_DELTA * gravity.0 * g_2.0 * r.normalize()) / (d_sq);
-        }
-    }
-    if let Some((t_2, g_2)) = phantom {
-        let r = *t_2 - position;
-        let d_sq = r.length_squared();
-        if d_sq > 30. {
-            velocity += (G * FIXED_TIME_DELTA * gravity.0 * g_2.0 * r.normalize()) / (d_sq);
-        }
-    }
+    // Held players sit still for the tick, and keep their velocity for when they're let go.
+    let held = world
+        .bodies
+        .iter_mut()
+        .filter(|body| {
+            matches!(players.get(body.entity), Ok(effects) if effects.holds_player(&registry))
+        })
+        .filter_map(|body| Some((body.entity, body.velocity.take()?)))
+        .collect::<Vec<_>>();
 
-    if velocity.length() > MAX_VELOCITY {
-        velocity = velocity.normalize() * MAX_VELOCITY;
-    }
+    let before = world.bodies.clone();
+    world.step();
 
-    let displacement = velocity * FIXED_TIME_DELTA;
-    let translation = position + displacement;
-    (velocity, position, translation)
+    for (before, body) in before.iter().zip(world.bodies.iter()) {
+        let held = held
+            .iter()
+            .find(|(entity, _)| *entity == body.entity)
+            .map(|(_, velocity)| *velocity);
+        let transform = match (body.velocity.or(held), body.path) {
+            (Some(velocity), _) => GravitationTransform::Velocity {
+                velocity,
+                start_position: Some(before.position),
+                target_position: Some(body.position),
+            },
+            (None, Some(_)) => GravitationTransform::Kinematic {
+                start_position: Some(before.position),
+                target_position: Some(body.position),
+            },
+            _ => continue,
+        };
+        commands.entity(body.entity).insert(transform);
+    }
 }
 
 pub(crate) fn adjust_rotation(mut query: Query<(Entity, &mut Transform, &GravitationTransform)>) {
@@ -146,21 +191,27 @@ pub(crate) fn adjust_rotation(mut query: Query<(Entity, &mut Transform, &Gravita
 pub(crate) fn smooth_movement(
     mut query: Query<(&mut Transform, &GravitationTransform)>,
     time: Res<Time>,
-    active_pickup: Res<ActivePickup>,
+    time_scale: Res<TimeScale>,
 ) {
-    if matches!(active_pickup.0, Some(PickupType::Teleport)) {
-        return;
-    }
-    let delta = time.delta_seconds();
+    let delta = time.delta_seconds() * time_scale.current();
     let proportion = delta / FIXED_TIME_DELTA;
     for (mut transform, gravitation_transform) in query.iter_mut() {
         if let GravitationTransform::Velocity {
-            velocity: _,
+            start_position,
+            target_position,
+            ..
+        }
+        | GravitationTransform::Kinematic {
             start_position,
             target_position,
         } = gravitation_transform
         {
             if let (Some(start), Some(end)) = (start_position, target_position) {
+                // Stepping through a paused world shows each tick as it lands.
+                if time_scale.paused {
+                    transform.translation = end.extend(transform.translation.z);
+                    continue;
+                }
                 let diff = *end - *start;
                 let diff = Vec3::new(diff.x, diff.y, 0.);
                 transform.translation += diff * proportion;
@@ -169,98 +220,411 @@ pub(crate) fn smooth_movement(
     }
 }
 
+pub const TRAJECTORY_COLOR: Color = Color::WHITE;
+pub const TRAJECTORY_PICKUP_COLOR: Color = Color::rgb(1., 0.85, 0.3);
+pub const TRAJECTORY_CRASH_COLOR: Color = Color::rgb(1., 0.25, 0.2);
+pub const TRAJECTORY_EXIT_COLOR: Color = Color::rgb(1., 0.55, 0.1);
+
 type TrajectoryQueryConditions = (With<TrajectoryPoint>, Without<GravitationalBody>);
 
+/// Keeps the number of each player's preview dots in line with the `TrajectorySettings`.
+pub(crate) fn sync_trajectory_points(
+    mut commands: Commands,
+    settings: Res<TrajectorySettings>,
+    multiplayer: Res<Multiplayer>,
+    points: Query<(Entity, &TrajectoryPoint)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, point) in points.iter() {
+        if point.0 >= multiplayer.players {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    for index in 0..multiplayer.players {
+        let existing = points
+            .iter()
+            .filter(|(_, point)| point.0 == index)
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        for entity in existing.iter().skip(settings.points) {
+            commands.entity(*entity).despawn_recursive();
+        }
+        for _ in existing.len()..settings.points {
+            commands.spawn((
+                MaterialMesh2dBundle {
+                    mesh: meshes.add(shape::RegularPolygon::new(3., 8).into()).into(),
+                    material: color_materials.add(TRAJECTORY_COLOR.into()),
+                    transform: Transform::default(),
+                    visibility: Visibility::INVISIBLE,
+                    ..default()
+                },
+                TrajectoryPoint(index),
+            ));
+        }
+    }
+}
+
+type PredictingPlayer<'a> = (
+    Entity,
+    &'a Player,
+    &'a Prediction,
+    &'a ActiveEffects,
+    &'a ThrustInput,
+    &'a Fuel,
+);
+
+/// Shows where each player would go if their previewed well were placed, or
+/// if the thrust they're holding were kept up. The dots turn gold once a
+/// pickup would be collected, and the preview stops at the first crash or
+/// boundary exit with a dot marking the spot.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn predict_trajectory(
-    mut trajectory_points: Query<(&mut Transform, &mut Visibility), TrajectoryQueryConditions>,
-    query: Query<
+    mut trajectory_points: Query<
         (
-            Entity,
-            &Transform,
-            &GravitationTransform,
-            &GravitationalBody,
+            &TrajectoryPoint,
+            &mut Transform,
+            &mut Visibility,
+            &Handle<ColorMaterial>,
         ),
-        Without<Deletable>,
+        TrajectoryQueryConditions,
     >,
-    player: Query<Entity, With<Player>>,
-    prediction: Res<Prediction>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    query: Query<BodyQueryItem>,
+    pickups: Query<(&Transform, &Pickup), Without<TrajectoryPoint>>,
+    wells: Query<(Entity, &PlacedWell)>,
+    rules: Res<WellRules>,
+    players: Query<PredictingPlayer>,
+    settings: Res<TrajectorySettings>,
+    boundary: Res<LevelBoundary>,
+    tick: Res<PhysicsTick>,
+    integration: Res<Integration>,
+    gameplay: Res<GameplaySettings>,
+    registry: Res<EffectRegistry>,
 ) {
-    let prediction = prediction.as_ref();
-    if let Ok(player) = player.get_single() {
-        if let Ok((
-            entity,
-            transform,
-            GravitationTransform::Velocity {
-                velocity,
-                start_position: _,
-                target_position,
-            },
-            grav_body,
-        )) = query.get(player)
+    // Knocked out players don't have anything left to preview.
+    for (point, _, mut v, _) in trajectory_points.iter_mut() {
+        if v.is_visible && !players.iter().any(|(_, player, ..)| player.0 == point.0) {
+            v.is_visible = false;
+        }
+    }
+
+    let mut initial = GravityWorld::from_bodies(query.iter());
+    initial.time = tick.time();
+    initial.integration = *integration;
+    initial.settings = *gameplay;
+
+    let mut pickup_grid = SpatialGrid::default();
+    for (transform, pickup) in pickups.iter() {
+        let center = transform.translation.truncate();
+        pickup_grid.insert_swept((center, pickup.0), center, center, pickup.0);
+    }
+
+    // One scratch world is stepped for every player, keeping its buffers.
+    let mut world = GravityWorld::default();
+    let mut previous = GravityWorld::default();
+    'players: for (entity, player, prediction, effects, thrust, fuel) in players.iter() {
+        world.clone_from(&initial);
+        // The preview uses up effects the same way placing the well would.
+        let mut effects = effects.clone();
+        let teleporting = matches!(prediction, Prediction::Insert(..)) && {
+            let mut placement = plain_well();
+            effects.place(&registry, &mut placement);
+            placement.teleports
+        };
+        if !teleporting {
+            if let Some(replaced) = well_to_replace(wells.iter(), player.0, &rules) {
+                world.bodies.retain(|body| body.entity != replaced);
+            }
+        }
+        if !matches!(world.get(entity), Some(body) if body.velocity.is_some()) {
+            continue;
+        }
+        // Held players don't burn fuel, just like in `apply_thrust`.
+        let thrusting =
+            thrust.0 != Vec2::ZERO && fuel.remaining > 0. && !effects.holds_player(&registry);
+        match prediction {
+            Prediction::Insert(prediction_pos, grav, black_hole) => {
+                // The teleport preview moves the player instead of adding a well.
+                if !teleporting {
+                    let phantom = WorldBody::fixed(
+                        Entity::from_raw(u32::MAX),
+                        *prediction_pos,
+                        grav.0,
+                        grav.1,
+                    );
+                    world.push(if black_hole.is_some() {
+                        phantom.uncapped()
+                    } else {
+                        phantom
+                    });
+                }
+            }
+            Prediction::None if thrusting => {}
+            Prediction::None => {
+                for (point, _, mut v, _) in trajectory_points.iter_mut() {
+                    if point.0 == player.0 {
+                        v.is_visible = false;
+                    }
+                }
+                continue;
+            }
+        }
+
+        let steps_per_point = ((settings.spacing / FIXED_TIME_DELTA).round() as usize).max(1);
+        let mut color = TRAJECTORY_COLOR;
+        let mut ended = false;
+        let mut fuel = *fuel;
+
+        for (_, mut t, mut vis, material) in trajectory_points
+            .iter_mut()
+            .filter(|(point, ..)| point.0 == player.0)
         {
-            match prediction {
-                Prediction::Insert(prediction_pos, grav) => {
-                    let mut trajectory_pos = *(target_position
-                        .as_ref()
-                        .unwrap_or(&transform.translation.xy()));
-                    let mut v = *velocity;
-                    for (mut t, mut vis) in trajectory_points.iter_mut() {
-                        let mut dist = 0.;
-                        loop {
-                            dist += FIXED_TIME_DELTA;
-                            if dist >= GAP_BETWEEN_TRAJECTORY {
-                                break;
-                            }
-                            let (vel, _, p) = process_gravity_trajectory(
-                                &v,
-                                trajectory_pos,
-                                query.iter(),
-                                entity,
-                                grav_body,
-                                Some((prediction_pos, grav)),
-                            );
-                            trajectory_pos = p;
-                            v = vel;
-                        }
-
-                        vis.is_visible = true;
-                        t.translation = Vec3::new(trajectory_pos.x, trajectory_pos.y, 0.);
+            if ended {
+                vis.is_visible = false;
+                continue;
+            }
+
+            let mut position = Vec2::ZERO;
+            for _ in 0..steps_per_point {
+                if thrusting {
+                    if let Some(velocity) = world.get_mut(entity).and_then(|b| b.velocity.as_mut())
+                    {
+                        *velocity += fuel.burn(thrust.0);
                     }
                 }
-                _ => {
-                    for (_, mut v) in trajectory_points.iter_mut() {
-                        v.is_visible = false;
+                previous.clone_from(&world);
+                world.step();
+                let (start, mut end, velocity) = match (previous.get(entity), world.get(entity)) {
+                    (Some(start), Some(end)) => (
+                        start.position,
+                        end.position,
+                        end.velocity.unwrap_or_default(),
+                    ),
+                    _ => continue 'players,
+                };
+
+                if let Some((body, contact)) = world.first_contact(&previous, entity) {
+                    let mut crash = CrashResponse {
+                        contact,
+                        black_hole: body.uncapped,
+                        position: end,
+                        velocity,
+                        destroy_body: false,
+                        survived: false,
+                    };
+                    effects.crash(&registry, &mut crash);
+                    if crash.destroy_body {
+                        world.bodies.retain(|b| b.entity != body.entity);
+                    }
+                    if !crash.survived {
+                        position = contact.point;
+                        color = TRAJECTORY_CRASH_COLOR;
+                        ended = true;
+                        break;
                     }
+                    end = crash.position;
+                    if let Some(player) = world.get_mut(entity) {
+                        player.position = crash.position;
+                        player.velocity = Some(crash.velocity);
+                    }
+                }
+
+                if end.cmplt(boundary.min).any() || end.cmpgt(boundary.max).any() {
+                    let mut exit = BoundaryResponse {
+                        min: boundary.min,
+                        max: boundary.max,
+                        position: end,
+                        velocity: world
+                            .get(entity)
+                            .and_then(|body| body.velocity)
+                            .unwrap_or_default(),
+                        survived: false,
+                    };
+                    effects.leave_boundary(&registry, &mut exit);
+                    if !exit.survived {
+                        position = end.clamp(boundary.min, boundary.max);
+                        color = TRAJECTORY_EXIT_COLOR;
+                        ended = true;
+                        break;
+                    }
+                    end = exit.position;
+                    if let Some(player) = world.get_mut(entity) {
+                        player.position = exit.position;
+                        player.velocity = Some(exit.velocity);
+                    }
+                }
+                position = end;
+
+                if color == TRAJECTORY_COLOR
+                    && pickup_grid.query_swept(start, end).any(|(center, radius)| {
+                        sweep_circle(start, end, *center, *center, *radius).is_some()
+                    })
+                {
+                    color = TRAJECTORY_PICKUP_COLOR;
+                }
+            }
+
+            vis.is_visible = true;
+            t.translation = Vec3::new(position.x, position.y, 0.);
+            if color_materials.get(material).map(|m| m.color) != Some(color) {
+                if let Some(material) = color_materials.get_mut(material) {
+                    material.color = color;
                 }
             }
         }
     }
 }
 
+pub struct CrashEvent(pub Entity, pub Contact);
+
+/// Puts a player somewhere else straight away, rather than easing them there
+/// over the next frames.
+pub(crate) fn move_player(
+    transform: &mut Transform,
+    gravitation_transform: &mut GravitationTransform,
+    position: Vec2,
+) {
+    transform.translation = position.extend(transform.translation.z);
+    if let GravitationTransform::Velocity {
+        start_position,
+        target_position,
+        ..
+    } = gravitation_transform
+    {
+        *start_position = Some(position);
+        *target_position = Some(position);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn check_crash(
     mut commands: Commands,
-    players: Query<&Transform, With<player::Player>>,
-    gravitational_bodies: Query<(Entity, &Transform, &GravitationalBody), Without<player::Player>>,
-    mut active_pickup: ResMut<ActivePickup>,
-    audio: Res<AudioChannel<ForegroundAudio>>,
-    assets: Res<GameAssets>,
+    mut players: Query<(
+        Entity,
+        &Player,
+        &mut Transform,
+        &mut GravitationTransform,
+        &mut ActiveEffects,
+    )>,
+    gravitational_bodies: Query<
+        (
+            Entity,
+            &Transform,
+            &GravitationTransform,
+            &GravitationalBody,
+            Option<&BlackHole>,
+        ),
+        Without<player::Player>,
+    >,
+    multiplayer: Res<Multiplayer>,
+    registry: Res<EffectRegistry>,
+    mut crashes: EventWriter<CrashEvent>,
+    mut near_misses: ResMut<NearMisses>,
+    mut score_events: EventWriter<ScoreEvent>,
 ) {
-    if matches!(active_pickup.0, Some(PickupType::Teleport)) {
-        return;
+    let mut grid = SpatialGrid::default();
+    for (entity, transforms, body_transform, body, black_hole) in gravitational_bodies.iter() {
+        let radius = black_hole.map(|b| b.event_horizon).unwrap_or(body.1);
+        let (center_start, center_end) = body_transform.last_step(transforms);
+        grid.insert_swept(
+            (
+                entity,
+                center_start,
+                center_end,
+                radius,
+                black_hole.is_some(),
+            ),
+            center_start,
+            center_end,
+            radius + NEAR_MISS_MARGIN,
+        );
     }
-    for player in players.iter() {
-        for (entity, transforms, body) in gravitational_bodies.iter() {
-            if player.translation.distance(transforms.translation) <= body.1 {
-                audio.play(assets.destroyed_audio.clone());
-                if matches!(active_pickup.0, Some(PickupType::PlanetKiller)) {
-                    commands.entity(entity).despawn_recursive();
-                    active_pickup.0 = None;
-                } else {
-                    commands.insert_resource(NextState(GameState::GameOver));
-                }
+
+    let mut nearby = vec![];
+    let mut crashed = vec![];
+
+    for (player_entity, player, mut transform, mut gravitation_transform, mut effects) in
+        players.iter_mut()
+    {
+        let (start, end) = gravitation_transform.last_step(&transform);
+        let candidates = grid.query_swept(start, end).collect::<Vec<_>>();
+        nearby.extend(
+            candidates
+                .iter()
+                .filter(|(_, center_start, center_end, radius, _)| {
+                    sweep_circle(
+                        start,
+                        end,
+                        *center_start,
+                        *center_end,
+                        radius + NEAR_MISS_MARGIN,
+                    )
+                    .is_some()
+                })
+                .map(|(entity, ..)| (player.0, *entity)),
+        );
+        let mut contacts = candidates
+            .into_iter()
+            .filter_map(
+                |(entity, center_start, center_end, radius, is_black_hole)| {
+                    sweep_circle(start, end, *center_start, *center_end, *radius)
+                        .map(|contact| (*entity, contact, *is_black_hole))
+                },
+            )
+            .collect::<Vec<_>>();
+        contacts.sort_by(|a, b| a.1.time.total_cmp(&b.1.time));
+
+        for (entity, contact, is_black_hole) in contacts {
+            crashed.push((player.0, entity));
+            crashes.send(CrashEvent(entity, contact));
+            let velocity = match gravitation_transform.as_ref() {
+                GravitationTransform::Velocity { velocity, .. } => *velocity,
+                _ => Vec2::ZERO,
+            };
+            let mut crash = CrashResponse {
+                contact,
+                black_hole: is_black_hole,
+                position: end,
+                velocity,
+                destroy_body: false,
+                survived: false,
+            };
+            effects.crash(&registry, &mut crash);
+            if crash.destroy_body {
+                commands.entity(entity).despawn_recursive();
+            }
+            if !crash.survived {
+                knock_out(&mut commands, &multiplayer, player_entity);
+                break;
+            }
+            if let GravitationTransform::Velocity { velocity, .. } = gravitation_transform.as_mut()
+            {
+                *velocity = crash.velocity;
+            }
+            // A player sent off somewhere else doesn't fly the rest of the step.
+            if crash.position != end {
+                move_player(&mut transform, &mut gravitation_transform, crash.position);
+                break;
             }
         }
     }
+
+    // A flyby counts once the player has left a body's range without crashing into it.
+    nearby.retain(|miss| !crashed.contains(miss));
+    let passed = near_misses
+        .0
+        .iter()
+        .filter(|miss| !nearby.contains(miss) && !crashed.contains(miss))
+        .filter(|(player, _)| players.iter().any(|(_, p, ..)| p.0 == *player));
+    for (player, _) in passed {
+        score_events.send(ScoreEvent::new(
+            *player,
+            ScoreKind::NearMiss,
+            NEAR_MISS_POINTS,
+        ));
+    }
+    near_misses.0 = nearby;
 }
 
 #[cfg(profile = "dev")]
@@ -295,24 +659,33 @@ pub fn set_sprite_to_radius(
 
 pub const DELAYED_ACTIVITY_FLASH_DURATION: f32 = 0.3;
 
-pub fn delayed_activity_flasher(
-    mut bodies: Query<(Entity, &mut Visibility, &mut DelayedActivity)>,
-    time: Res<Time>,
+pub(crate) fn tick_delayed_activity(
     mut commands: Commands,
+    mut bodies: Query<(Entity, &mut DelayedActivity)>,
 ) {
-    if bodies.is_empty() {
-        return;
+    for (entity, mut delay) in bodies.iter_mut() {
+        delay.0 -= FIXED_TIME_DELTA;
+        if delay.0 < 0. {
+            commands.entity(entity).remove::<DelayedActivity>();
+        }
     }
+}
+
+pub fn delayed_activity_flasher(
+    mut bodies: Query<(&mut Visibility, Option<&DelayedActivity>), With<GravitationalBody>>,
+    removed: RemovedComponents<DelayedActivity>,
+    time: Res<Time>,
+) {
     let division = time.elapsed_seconds() / DELAYED_ACTIVITY_FLASH_DURATION;
     let division = division - division.floor();
     let is_on = division > 0.5;
-    let delta = time.delta_seconds();
-    for (entity, mut visibility, mut delay) in bodies.iter_mut() {
-        delay.0 -= delta;
-        if delay.0 < 0. {
-            commands.entity(entity).remove::<DelayedActivity>();
+    for entity in removed.iter() {
+        if let Ok((mut visibility, _)) = bodies.get_mut(entity) {
             visibility.is_visible = true;
-        } else {
+        }
+    }
+    for (mut visibility, delay) in bodies.iter_mut() {
+        if delay.is_some() {
             visibility.is_visible = is_on;
         }
     }
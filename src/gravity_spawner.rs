@@ -2,132 +2,506 @@ use bevy::prelude::*;
 use bevy::render::camera::RenderTarget;
 
 use crate::assets::GameAssets;
-use crate::gravity::{self, GravitationTransform, GravitationalBody};
-use crate::pickup::{ActivePickup, PickupType};
-use crate::player::Player;
+use crate::controls::{Action, Controls, VirtualCursor};
+use crate::effects::{ActiveEffects, EffectRegistry, WellPlacement};
+use crate::gravity::{
+    self, BlackHole, GravitationTransform, GravitationalBody, GAP_BETWEEN_TRAJECTORY,
+};
+use crate::multiplayer::Multiplayer;
+use crate::pickup::PickupType;
+use crate::player::{Player, INVERTER_TINT};
+use crate::replay::{PhysicsTick, Placement, ReplayMode, ReplayRecording};
+use crate::touch::TouchPointer;
+use serde::{Deserialize, Serialize};
 
 #[derive(Component)]
 pub struct Deletable;
 
 #[derive(Component)]
-pub struct TrajectoryPoint;
+pub struct PlacedWell {
+    /// The player who placed it.
+    pub owner: usize,
+    pub placed_at: u64,
+    pub expires_at: Option<u64>,
+}
+
+/// How many wells each player may keep at once, and how long each one lasts.
+#[derive(Resource, Deserialize, Debug, Clone, Copy)]
+pub struct WellRules {
+    pub budget: usize,
+    #[serde(default)]
+    pub lifetime: Option<f32>,
+}
+
+impl Default for WellRules {
+    fn default() -> Self {
+        Self {
+            budget: 1,
+            lifetime: None,
+        }
+    }
+}
+
+impl WellRules {
+    /// The tick a well placed on `placed_at` runs out, if wells don't last forever.
+    pub fn expires_at(&self, placed_at: u64) -> Option<u64> {
+        self.lifetime
+            .map(|lifetime| placed_at + (lifetime / gravity::FIXED_TIME_DELTA).round() as u64)
+    }
+}
+
+const WELL_MASS: f32 = 10000.;
+const WELL_RADIUS: f32 = 10.;
+const WELL_REMOVAL_SLACK: f32 = 15.;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WellAction {
+    Place,
+    Remove,
+}
+
+/// Released placements waiting for the next fixed tick to apply them, by player.
+#[derive(Resource, Default)]
+pub struct PendingPlacements(pub Vec<(usize, WellAction, Vec2)>);
 
-#[derive(Resource)]
+/// The well of `owner` that a new placement would push out, once their budget
+/// is used up.
+pub fn well_to_replace<'a>(
+    wells: impl Iterator<Item = (Entity, &'a PlacedWell)>,
+    owner: usize,
+    rules: &WellRules,
+) -> Option<Entity> {
+    let wells = wells
+        .filter(|(_, well)| well.owner == owner)
+        .collect::<Vec<_>>();
+    if wells.len() < rules.budget {
+        return None;
+    }
+    wells
+        .into_iter()
+        .min_by_key(|(entity, well)| (well.placed_at, *entity))
+        .map(|(entity, _)| entity)
+}
+
+/// One dot of a player's placement preview.
+#[derive(Component)]
+pub struct TrajectoryPoint(pub usize);
+
+/// How far ahead the placement preview looks, and how closely its dots are spaced.
+#[derive(Resource, Deserialize, Debug, Clone, Copy)]
+pub struct TrajectorySettings {
+    pub points: usize,
+    /// Seconds of flight between each dot.
+    #[serde(default = "default_trajectory_spacing")]
+    pub spacing: f32,
+}
+
+impl Default for TrajectorySettings {
+    fn default() -> Self {
+        Self {
+            points: 10,
+            spacing: GAP_BETWEEN_TRAJECTORY,
+        }
+    }
+}
+
+fn default_trajectory_spacing() -> f32 {
+    GAP_BETWEEN_TRAJECTORY
+}
+
+/// The well a player is about to place, while they hold the button down.
+#[derive(Component)]
 pub enum Prediction {
     None,
-    Insert(Vec2, GravitationalBody),
+    Insert(Vec2, GravitationalBody, Option<BlackHole>),
 }
 
+type SpawningPlayer<'a> = (
+    Entity,
+    &'a Player,
+    &'a VirtualCursor,
+    &'a ActiveEffects,
+    &'a mut Prediction,
+    &'a GravitationTransform,
+    &'a Transform,
+);
+
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn gravity_spawner(
     mut commands: Commands,
-    buttons: Res<Input<MouseButton>>,
+    controls: Controls,
+    touch: Res<TouchPointer>,
     windows: Res<Windows>,
     q_camera: Query<(&Camera, &GlobalTransform)>,
-    existing_gravity: Query<Entity, With<Deletable>>,
-    assets: Res<GameAssets>,
-    prediction: Res<Prediction>,
-    mut active_pickup: ResMut<ActivePickup>,
-    players: Query<(Entity, &GravitationTransform, &Transform), With<Player>>,
+    multiplayer: Res<Multiplayer>,
+    mut players: Query<SpawningPlayer>,
+    mut pending: ResMut<PendingPlacements>,
+    replay_mode: Res<ReplayMode>,
+    registry: Res<EffectRegistry>,
 ) {
-    let spawning = buttons.just_released(MouseButton::Left);
-    let testing = buttons.pressed(MouseButton::Left);
-    let initialized = buttons.just_pressed(MouseButton::Left);
-
-    if (spawning || testing) && !initialized && matches!(*prediction, Prediction::None) {
+    if matches!(*replay_mode, ReplayMode::Replaying(_)) {
         return;
     }
 
-    if !spawning && !testing {
-        return;
+    for (entity, player, cursor, effects, mut prediction, gravitation, transform) in
+        players.iter_mut()
+    {
+        let controls = controls.player(player.0, multiplayer.players);
+        // The touch screen and mouse belong to the first player.
+        let touch = if player.0 == 0 {
+            *touch
+        } else {
+            TouchPointer::default()
+        };
+
+        let pointer = || {
+            let (camera, camera_transform) = q_camera.single();
+            if let Some(position) = touch.position {
+                return screen_to_world(camera, camera_transform, &windows, |_| Some(position));
+            }
+            if cursor.active {
+                return Some(cursor.position);
+            }
+            if player.0 != 0 {
+                return None;
+            }
+            screen_to_world(camera, camera_transform, &windows, |window| {
+                window.cursor_position()
+            })
+        };
+
+        if touch.pinching && !matches!(*prediction, Prediction::None) {
+            *prediction = Prediction::None;
+            continue;
+        }
+
+        if controls.just_released(Action::Remove) {
+            if let Some(world_pos) = pointer() {
+                pending.0.push((player.0, WellAction::Remove, world_pos));
+            }
+        }
+
+        let spawning = controls.just_released(Action::Place) || touch.released;
+        let testing =
+            controls.pressed(Action::Place) || (touch.position.is_some() && !touch.released);
+        let initialized = controls.just_pressed(Action::Place) || touch.just_started;
+
+        if (spawning || testing) && !initialized && matches!(*prediction, Prediction::None) {
+            continue;
+        }
+
+        if !spawning && !testing {
+            continue;
+        }
+
+        let world_pos = match pointer() {
+            Some(world_pos) => world_pos,
+            None => {
+                *prediction = Prediction::None;
+                continue;
+            }
+        };
+
+        // The effects are only used up once the placement is applied.
+        let mut placement = plain_well();
+        effects.clone().place(&registry, &mut placement);
+
+        if placement.teleports {
+            teleport(&mut commands, entity, gravitation, transform, world_pos);
+        }
+
+        if spawning && !matches!(*prediction, Prediction::None) {
+            *prediction = Prediction::None;
+            pending.0.push((player.0, WellAction::Place, world_pos));
+        } else {
+            *prediction = Prediction::Insert(world_pos, placement.body, placement.black_hole);
+        }
     }
+}
 
-    let (camera, camera_transform) = q_camera.single();
+/// A well as it's placed without any effects changing it.
+pub fn plain_well() -> WellPlacement {
+    WellPlacement {
+        body: GravitationalBody(WELL_MASS, WELL_RADIUS),
+        black_hole: None,
+        teleports: false,
+    }
+}
 
+/// Moves a teleporting player to `position`, keeping their velocity.
+fn teleport(
+    commands: &mut Commands,
+    entity: Entity,
+    gravitation: &GravitationTransform,
+    transform: &Transform,
+    position: Vec2,
+) {
+    if let GravitationTransform::Velocity { velocity, .. } = gravitation {
+        commands.entity(entity).insert((
+            transform.with_translation(Vec3::new(position.x, position.y, 0.)),
+            GravitationTransform::Velocity {
+                velocity: *velocity,
+                start_position: Some(position),
+                target_position: Some(position),
+            },
+        ));
+    }
+}
+
+/// Converts a position on the camera's window, picked by `screen_position`, to world space.
+fn screen_to_world(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    windows: &Windows,
+    screen_position: impl FnOnce(&Window) -> Option<Vec2>,
+) -> Option<Vec2> {
     // get the window that the camera is displaying to (or the primary window)
     let wnd = if let RenderTarget::Window(id) = camera.target {
-        windows.get(id).unwrap()
+        windows.get(id)?
     } else {
-        windows.get_primary().unwrap()
+        windows.get_primary()?
     };
 
-    let screen_pos = wnd.cursor_position();
-
-    // check if the cursor is inside the window and get its position
-    if let Some(screen_pos) = screen_pos {
-        // get the size of the window
-        let window_size = Vec2::new(wnd.width(), wnd.height());
-
-        // convert screen position [0..resolution] to ndc [-1..1] (gpu coordinates)
-        let ndc = (screen_pos / window_size) * 2.0 - Vec2::ONE;
-
-        // matrix for undoing the projection and camera transform
-        let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
-
-        // use it to convert ndc to world-space coordinates
-        let world_pos = ndc_to_world.project_point3(ndc.extend(-1.0));
-
-        // reduce it to a 2D value
-        let world_pos: Vec2 = world_pos.truncate();
-
-        let (mut possible_body, image) =
-            (GravitationalBody(10000., 10.), assets.small_planet.clone());
-
-        if matches!(active_pickup.0, Some(PickupType::Teleport)) {
-            for (entity, player, transform) in players.iter() {
-                if let GravitationTransform::Velocity {
-                    velocity,
-                    start_position: _,
-                    target_position: _,
-                } = player
-                {
-                    commands.entity(entity).insert((
-                        transform.with_translation(Vec3::new(world_pos.x, world_pos.y, 0.)),
-                        GravitationTransform::Velocity {
-                            velocity: *velocity,
-                            start_position: Some(world_pos),
-                            target_position: Some(world_pos),
-                        },
-                    ));
-                }
+    // check if the position is inside the window
+    let screen_pos = screen_position(wnd)?;
+
+    // get the size of the window
+    let window_size = Vec2::new(wnd.width(), wnd.height());
+
+    // convert screen position [0..resolution] to ndc [-1..1] (gpu coordinates)
+    let ndc = (screen_pos / window_size) * 2.0 - Vec2::ONE;
+
+    // matrix for undoing the projection and camera transform
+    let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
+
+    // use it to convert ndc to world-space coordinates
+    let world_pos = ndc_to_world.project_point3(ndc.extend(-1.0));
+
+    // reduce it to a 2D value
+    Some(world_pos.truncate())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn apply_placements(
+    mut commands: Commands,
+    mut pending: ResMut<PendingPlacements>,
+    wells: Query<(Entity, &PlacedWell, &Transform)>,
+    mut players: Query<(
+        Entity,
+        &Player,
+        &mut ActiveEffects,
+        &GravitationTransform,
+        &Transform,
+    )>,
+    tick: Res<PhysicsTick>,
+    mut recording: ResMut<ReplayRecording>,
+    rules: Res<WellRules>,
+    registry: Res<EffectRegistry>,
+) {
+    let mut removed = wells
+        .iter()
+        .filter(|(_, well, _)| matches!(well.expires_at, Some(expires_at) if expires_at <= tick.0))
+        .map(|(entity, _, _)| entity)
+        .collect::<Vec<_>>();
+    for (owner, action, position) in pending.0.drain(..) {
+        // Placements from a player who has been knocked out are dropped.
+        let (entity, _, mut effects, gravitation, transform) =
+            match players.iter_mut().find(|(_, player, ..)| player.0 == owner) {
+                Some(player) => player,
+                None => continue,
+            };
+
+        let mut placement = plain_well();
+        let mut remaining_effects = effects.clone();
+        let used = match action {
+            WellAction::Place => remaining_effects.place(&registry, &mut placement),
+            WellAction::Remove => None,
+        };
+        // Without a well to build, the effects are kept for later.
+        let built = action == WellAction::Place && (placement.teleports || rules.budget > 0);
+
+        recording.0.placements.push(Placement {
+            tick: tick.0,
+            action,
+            position,
+            pickup: used.filter(|_| built).map(PickupType::Effect),
+            seed: recording.0.seed,
+            player: owner,
+        });
+
+        if action == WellAction::Remove {
+            let closest = wells
+                .iter()
+                .filter(|(entity, well, _)| well.owner == owner && !removed.contains(entity))
+                .map(|(entity, _, transform)| {
+                    (entity, transform.translation.truncate().distance(position))
+                })
+                .filter(|(_, distance)| *distance <= WELL_RADIUS + WELL_REMOVAL_SLACK)
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((entity, _)) = closest {
+                commands.entity(entity).despawn_recursive();
+                removed.push(entity);
             }
-            possible_body.0 = 0.;
+            continue;
         }
 
-        if spawning && !matches!(*prediction, Prediction::None) {
-            commands.insert_resource(Prediction::None);
-
-            if matches!(active_pickup.0, Some(PickupType::Teleport)) {
-                active_pickup.0 = None;
-            } else {
-                for entity in existing_gravity.iter() {
-                    commands.entity(entity).despawn_recursive();
-                }
-
-                commands.spawn((
-                    SpriteBundle {
-                        sprite: Sprite {
-                            custom_size: Some(Vec2::ONE * 30.),
-                            ..Default::default()
-                        },
-                        texture: image,
-                        transform: Transform::from_translation(Vec3::new(
-                            world_pos.x,
-                            world_pos.y,
-                            0.,
-                        )),
-                        ..default()
-                    },
-                    possible_body,
-                    gravity::GravitationTransform::Static,
-                    Deletable,
-                    crate::level::LevelEntity,
-                ));
-            }
+        if !built {
+            continue;
+        }
+        *effects = remaining_effects;
+
+        if placement.teleports {
+            teleport(&mut commands, entity, gravitation, transform, position);
+            continue;
+        }
+
+        let remaining = wells
+            .iter()
+            .filter(|(entity, _, _)| !removed.contains(entity))
+            .map(|(entity, well, _)| (entity, well));
+        if let Some(entity) = well_to_replace(remaining, owner, &rules) {
+            commands.entity(entity).despawn_recursive();
+            removed.push(entity);
+        }
+
+        let mut well = commands.spawn((
+            SpatialBundle::from_transform(Transform::from_translation(Vec3::new(
+                position.x, position.y, 0.,
+            ))),
+            placement.body,
+            gravity::GravitationTransform::Static,
+            Deletable,
+            PlacedWell {
+                owner,
+                placed_at: tick.0,
+                expires_at: rules.expires_at(tick.0),
+            },
+            crate::level::LevelEntity,
+        ));
+        if let Some(black_hole) = placement.black_hole {
+            well.insert(black_hole);
+        }
+    }
+}
+
+pub(crate) fn expire_wells(
+    mut commands: Commands,
+    wells: Query<(Entity, &PlacedWell)>,
+    tick: Res<PhysicsTick>,
+) {
+    for (entity, well) in wells.iter() {
+        if matches!(well.expires_at, Some(expires_at) if expires_at <= tick.0) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+pub(crate) fn dress_placed_wells(
+    mut commands: Commands,
+    wells: Query<(Entity, &GravitationalBody, Option<&BlackHole>), Added<Deletable>>,
+    assets: Res<GameAssets>,
+) {
+    for (entity, body, black_hole) in wells.iter() {
+        let (size, texture) = if black_hole.is_some() {
+            (body.1 * 2., assets.hole.clone())
         } else {
-            commands.insert_resource(Prediction::Insert(world_pos, possible_body));
+            (30., assets.small_planet.clone())
+        };
+        commands.entity(entity).insert((
+            Sprite {
+                custom_size: Some(Vec2::ONE * size),
+                color: if body.0 < 0. {
+                    INVERTER_TINT
+                } else {
+                    Color::WHITE
+                },
+                ..Default::default()
+            },
+            texture,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn well(owner: usize, placed_at: u64) -> PlacedWell {
+        PlacedWell {
+            owner,
+            placed_at,
+            expires_at: None,
         }
-    } else {
-        commands.insert_resource(Prediction::None);
+    }
+
+    fn rules(budget: usize, lifetime: Option<f32>) -> WellRules {
+        WellRules { budget, lifetime }
+    }
+
+    #[test]
+    fn wells_under_budget_are_kept() {
+        let wells = [(Entity::from_raw(1), well(0, 10))];
+
+        let replaced = well_to_replace(wells.iter().map(|(e, w)| (*e, w)), 0, &rules(2, None));
+
+        assert_eq!(replaced, None);
+    }
+
+    #[test]
+    fn oldest_of_the_owners_wells_is_replaced_first() {
+        let wells = [
+            (Entity::from_raw(1), well(0, 30)),
+            (Entity::from_raw(2), well(1, 5)),
+            (Entity::from_raw(3), well(0, 20)),
+            (Entity::from_raw(4), well(0, 25)),
+        ];
+
+        let replaced = well_to_replace(wells.iter().map(|(e, w)| (*e, w)), 0, &rules(3, None));
+
+        assert_eq!(replaced, Some(Entity::from_raw(3)));
+    }
+
+    #[test]
+    fn other_players_wells_dont_count_towards_the_budget() {
+        let wells = [
+            (Entity::from_raw(1), well(1, 5)),
+            (Entity::from_raw(2), well(1, 6)),
+            (Entity::from_raw(3), well(0, 20)),
+        ];
+
+        let replaced = well_to_replace(wells.iter().map(|(e, w)| (*e, w)), 0, &rules(2, None));
+
+        assert_eq!(replaced, None);
+    }
+
+    #[test]
+    fn wells_placed_on_the_same_tick_are_replaced_in_spawn_order() {
+        let wells = [
+            (Entity::from_raw(7), well(0, 10)),
+            (Entity::from_raw(4), well(0, 10)),
+        ];
+
+        let replaced = well_to_replace(wells.iter().map(|(e, w)| (*e, w)), 0, &rules(2, None));
+
+        assert_eq!(replaced, Some(Entity::from_raw(4)));
+    }
+
+    #[test]
+    fn lifetime_is_rounded_to_whole_ticks() {
+        let ticks_per_second = (1. / gravity::FIXED_TIME_DELTA).round() as u64;
+
+        assert_eq!(rules(1, None).expires_at(100), None);
+        assert_eq!(
+            rules(1, Some(2.)).expires_at(100),
+            Some(100 + 2 * ticks_per_second)
+        );
+        assert_eq!(
+            rules(1, Some(0.4 * gravity::FIXED_TIME_DELTA)).expires_at(100),
+            Some(100)
+        );
+        assert_eq!(
+            rules(1, Some(0.6 * gravity::FIXED_TIME_DELTA)).expires_at(100),
+            Some(101)
+        );
     }
 }
@@ -0,0 +1,579 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gameplay_settings::GameplaySettings,
+    gravity::{BodyQueryItem, GravitationTransform, FIXED_TIME_DELTA},
+    planet_path::{PathCenter, PlanetPath},
+    spatial_index::QuadTree,
+    sweep::{sweep_circle, Contact},
+};
+
+/// Pairs closer than this don't pull on each other, so close passes can't fling bodies away.
+const MIN_DISTANCE_SQUARED: f32 = 30.;
+
+/// Below this many bodies, summing every pair directly is both exact and quick.
+const BARNES_HUT_MIN_BODIES: usize = 64;
+
+/// The pull on a body of `mass` towards one of `other_mass` that is `offset` away.
+fn pull(gravity: f32, offset: Vec2, mass: f32, other_mass: f32) -> Vec2 {
+    let d_sq = offset.length_squared();
+    if d_sq > MIN_DISTANCE_SQUARED {
+        gravity * mass * other_mass * offset.normalize() / d_sq
+    } else {
+        Vec2::ZERO
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Integrator {
+    /// Semi-implicit Euler, one force evaluation per substep.
+    #[default]
+    Euler,
+    /// Velocity Verlet, two force evaluations per substep and no long term energy drift.
+    VelocityVerlet,
+    /// Classic fourth order Runge-Kutta, four force evaluations per substep.
+    Rk4,
+}
+
+impl Integrator {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "euler" => Some(Self::Euler),
+            "verlet" => Some(Self::VelocityVerlet),
+            "rk4" => Some(Self::Rk4),
+            _ => None,
+        }
+    }
+}
+
+/// How gravity is integrated, shared by the game and the trajectory preview so
+/// the preview always shows the path the player will actually take.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Integration {
+    pub integrator: Integrator,
+    /// How many times each fixed tick is split up.
+    pub substeps: u32,
+}
+
+impl Default for Integration {
+    fn default() -> Self {
+        Self {
+            integrator: Integrator::default(),
+            substeps: 1,
+        }
+    }
+}
+
+impl Integration {
+    /// Reads `--integrator <euler|verlet|rk4>` and `--substeps <n>`.
+    pub fn from_args(args: impl Iterator<Item = String>) -> Self {
+        let args = args.collect::<Vec<_>>();
+        let value = |flag: &str| {
+            args.iter()
+                .skip_while(|arg| *arg != flag)
+                .nth(1)
+                .map(|value| value.as_str())
+        };
+        let default = Self::default();
+        Self {
+            integrator: value("--integrator")
+                .and_then(Integrator::from_name)
+                .unwrap_or(default.integrator),
+            substeps: value("--substeps")
+                .and_then(|substeps| substeps.parse().ok())
+                .unwrap_or(default.substeps),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WorldBody {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub velocity: Option<Vec2>,
+    pub mass: f32,
+    pub radius: f32,
+    /// Pull from this body isn't limited by the maximum velocity.
+    pub uncapped: bool,
+    pub path: Option<PlanetPath>,
+}
+
+impl WorldBody {
+    pub fn fixed(entity: Entity, position: Vec2, mass: f32, radius: f32) -> Self {
+        Self {
+            entity,
+            position,
+            velocity: None,
+            mass,
+            radius,
+            uncapped: false,
+            path: None,
+        }
+    }
+
+    pub fn moving(entity: Entity, position: Vec2, velocity: Vec2, mass: f32, radius: f32) -> Self {
+        Self {
+            entity,
+            position,
+            velocity: Some(velocity),
+            mass,
+            radius,
+            uncapped: false,
+            path: None,
+        }
+    }
+
+    pub fn uncapped(self) -> Self {
+        Self {
+            uncapped: true,
+            ..self
+        }
+    }
+
+    pub fn with_path(self, path: PlanetPath) -> Self {
+        Self {
+            path: Some(path),
+            velocity: None,
+            ..self
+        }
+    }
+}
+
+/// A plain snapshot of every gravitational body, stepped without touching the ECS.
+#[derive(Debug, Default)]
+pub struct GravityWorld {
+    pub bodies: Vec<WorldBody>,
+    /// Physics time in seconds, used to place bodies that follow a path.
+    pub time: f32,
+    pub integration: Integration,
+    pub settings: GameplaySettings,
+}
+
+impl Clone for GravityWorld {
+    fn clone(&self) -> Self {
+        Self {
+            bodies: self.bodies.clone(),
+            time: self.time,
+            integration: self.integration,
+            settings: self.settings,
+        }
+    }
+
+    /// Reuses the body buffer, so a world can be copied into every step.
+    fn clone_from(&mut self, source: &Self) {
+        self.bodies.clone_from(&source.bodies);
+        self.time = source.time;
+        self.integration = source.integration;
+        self.settings = source.settings;
+    }
+}
+
+impl GravityWorld {
+    pub fn new(bodies: Vec<WorldBody>) -> Self {
+        Self {
+            bodies,
+            ..default()
+        }
+    }
+
+    pub fn from_bodies<'a>(bodies: impl Iterator<Item = BodyQueryItem<'a>>) -> Self {
+        Self::new(
+            bodies
+                .map(
+                    |(entity, transform, gravitation_transform, body, black_hole, path)| {
+                        let position = gravitation_transform.simulated_position(transform);
+                        let body = match gravitation_transform {
+                            GravitationTransform::Velocity { velocity, .. } => {
+                                WorldBody::moving(entity, position, *velocity, body.0, body.1)
+                            }
+                            _ => WorldBody::fixed(entity, position, body.0, body.1),
+                        };
+                        let body = match path {
+                            Some(path) => body.with_path(*path),
+                            None => body,
+                        };
+                        match black_hole {
+                            Some(black_hole) => WorldBody {
+                                radius: black_hole.event_horizon,
+                                ..body.uncapped()
+                            },
+                            None => body,
+                        }
+                    },
+                )
+                .collect(),
+        )
+    }
+
+    pub fn push(&mut self, body: WorldBody) {
+        self.bodies.push(body);
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&WorldBody> {
+        self.bodies.iter().find(|b| b.entity == entity)
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut WorldBody> {
+        self.bodies.iter_mut().find(|b| b.entity == entity)
+    }
+
+    /// Returns the acceleration of every body towards regular bodies and towards
+    /// uncapped bodies separately, with the bodies at `positions`.
+    ///
+    /// Busy worlds group distant regular bodies together with a Barnes–Hut tree.
+    fn accelerations(&self, positions: &[Vec2]) -> Vec<(Vec2, Vec2)> {
+        let use_tree = self.bodies.len() >= BARNES_HUT_MIN_BODIES;
+        let (members, direct): (Vec<usize>, Vec<usize>) =
+            (0..self.bodies.len()).partition(|index| {
+                let body = &self.bodies[*index];
+                use_tree && !body.uncapped && body.mass > 0.
+            });
+        let masses = self.bodies.iter().map(|b| b.mass).collect::<Vec<_>>();
+        let gravity = self.settings.gravity;
+        let tree = QuadTree::build(positions, &masses, members);
+
+        self.bodies
+            .iter()
+            .zip(positions)
+            .enumerate()
+            .map(|(index, (body, position))| {
+                let mut capped = Vec2::ZERO;
+                let mut uncapped = Vec2::ZERO;
+                if body.velocity.is_none() {
+                    return (capped, uncapped);
+                }
+                tree.visit(*position, index, |other_position, mass| {
+                    capped += pull(gravity, other_position - *position, body.mass, mass);
+                });
+                for other in direct.iter().filter(|other| **other != index) {
+                    let pull = pull(
+                        gravity,
+                        positions[*other] - *position,
+                        body.mass,
+                        masses[*other],
+                    );
+                    if self.bodies[*other].uncapped {
+                        uncapped += pull;
+                    } else {
+                        capped += pull;
+                    }
+                }
+                (capped, uncapped)
+            })
+            .collect()
+    }
+
+    /// Adds `step * scale` to the value of each moving body, leaving the rest alone.
+    fn offset(&self, values: &[Vec2], step: &[Vec2], scale: f32) -> Vec<Vec2> {
+        self.bodies
+            .iter()
+            .zip(values.iter().zip(step))
+            .map(|(body, (value, step))| match body.velocity {
+                Some(_) => *value + *step * scale,
+                None => *value,
+            })
+            .collect()
+    }
+
+    /// Clamps the speed of each moving body to the maximum velocity, unless an
+    /// uncapped body is pulling it harder than everything else combined.
+    fn limit(&self, velocities: Vec<Vec2>, accelerations: &[(Vec2, Vec2)]) -> Vec<Vec2> {
+        let max_velocity = self.settings.max_velocity;
+        velocities
+            .into_iter()
+            .zip(accelerations)
+            .map(|(velocity, (capped, uncapped))| {
+                if uncapped.length() <= capped.length() && velocity.length() > max_velocity {
+                    velocity.normalize() * max_velocity
+                } else {
+                    velocity
+                }
+            })
+            .collect()
+    }
+
+    /// Moves each moving body along `velocities` for `duration` and puts path
+    /// bodies where they are at `time`.
+    fn drift(
+        &self,
+        positions: &[Vec2],
+        velocities: &[Vec2],
+        duration: f32,
+        time: f32,
+    ) -> Vec<Vec2> {
+        let mut next = self.offset(positions, velocities, duration);
+        self.resolve_paths(&mut next, time);
+        next
+    }
+
+    /// Advances every moving body by one fixed tick, split into the configured
+    /// number of substeps. All bodies read the positions from the start of each
+    /// substep, so ordering does not matter. Bodies on a path are moved to where
+    /// their path puts them.
+    pub fn step(&mut self) {
+        let substeps = self.integration.substeps.max(1);
+        let h = FIXED_TIME_DELTA / substeps as f32;
+        for _ in 0..substeps {
+            self.substep(h);
+        }
+    }
+
+    fn substep(&mut self, h: f32) {
+        let time = self.time;
+        let x0 = self.bodies.iter().map(|b| b.position).collect::<Vec<_>>();
+        let v0 = self
+            .bodies
+            .iter()
+            .map(|b| b.velocity.unwrap_or(Vec2::ZERO))
+            .collect::<Vec<_>>();
+        let a0 = self.accelerations(&x0);
+        let total = |a: &[(Vec2, Vec2)]| {
+            a.iter()
+                .map(|(capped, uncapped)| *capped + *uncapped)
+                .collect::<Vec<_>>()
+        };
+        let k1v = total(&a0);
+
+        let (x, v) = match self.integration.integrator {
+            Integrator::Euler => {
+                let v = self.limit(self.offset(&v0, &k1v, h), &a0);
+                let x = self.drift(&x0, &v, h, time + h);
+                (x, v)
+            }
+            Integrator::VelocityVerlet => {
+                let half_kick = self.offset(&v0, &k1v, h / 2.);
+                let x = self.drift(&x0, &half_kick, h, time + h);
+                let k2v = total(&self.accelerations(&x));
+                let v = self.offset(&half_kick, &k2v, h / 2.);
+                (x, v)
+            }
+            Integrator::Rk4 => {
+                let x2 = self.drift(&x0, &v0, h / 2., time + h / 2.);
+                let v2 = self.offset(&v0, &k1v, h / 2.);
+                let k2v = total(&self.accelerations(&x2));
+                let x3 = self.drift(&x0, &v2, h / 2., time + h / 2.);
+                let v3 = self.offset(&v0, &k2v, h / 2.);
+                let k3v = total(&self.accelerations(&x3));
+                let x4 = self.drift(&x0, &v3, h, time + h);
+                let v4 = self.offset(&v0, &k3v, h);
+                let k4v = total(&self.accelerations(&x4));
+
+                let weighted = |k1: &[Vec2], k2: &[Vec2], k3: &[Vec2], k4: &[Vec2]| {
+                    (0..k1.len())
+                        .map(|i| (k1[i] + 2. * k2[i] + 2. * k3[i] + k4[i]) / 6.)
+                        .collect::<Vec<_>>()
+                };
+                let x = self.drift(&x0, &weighted(&v0, &v2, &v3, &v4), h, time + h);
+                let v = self.offset(&v0, &weighted(&k1v, &k2v, &k3v, &k4v), h);
+                (x, v)
+            }
+        };
+
+        self.time = time + h;
+        let v = self.limit(v, &a0);
+        for (body, (position, velocity)) in self.bodies.iter_mut().zip(x.into_iter().zip(v)) {
+            body.position = position;
+            if body.velocity.is_some() {
+                body.velocity = Some(velocity);
+            }
+        }
+    }
+
+    /// Puts every path body where it should be at `time`. Orbits around other
+    /// path bodies are resolved first, so moons follow their planets.
+    fn resolve_paths(&self, positions: &mut [Vec2], time: f32) {
+        let resolved = (0..self.bodies.len())
+            .map(|index| self.path_position(positions, index, time, self.bodies.len()))
+            .collect::<Vec<_>>();
+        for (position, resolved) in positions.iter_mut().zip(resolved) {
+            if let Some(resolved) = resolved {
+                *position = resolved;
+            }
+        }
+    }
+
+    /// Where a path body should be at `time`, with `depth` guarding against
+    /// orbits that go round in a cycle.
+    fn path_position(
+        &self,
+        positions: &[Vec2],
+        index: usize,
+        time: f32,
+        depth: usize,
+    ) -> Option<Vec2> {
+        let path = self.bodies[index].path?;
+        let center = match path.center() {
+            Some(PathCenter::Body(entity)) => {
+                let center = self.bodies.iter().position(|b| b.entity == entity)?;
+                if depth > 0 {
+                    self.path_position(positions, center, time, depth - 1)
+                        .unwrap_or(positions[center])
+                } else {
+                    positions[center]
+                }
+            }
+            Some(PathCenter::Point(point)) => point,
+            None => Vec2::ZERO,
+        };
+        Some(path.position_at(time, center))
+    }
+
+    /// The first body that `entity` touched on its way from where it was in
+    /// `previous` to where it is now, if any. `previous` has to be this world
+    /// before its last step, which keeps every body at the same index.
+    pub fn first_contact(
+        &self,
+        previous: &GravityWorld,
+        entity: Entity,
+    ) -> Option<(WorldBody, Contact)> {
+        debug_assert_eq!(previous.bodies.len(), self.bodies.len());
+        let index = self.bodies.iter().position(|b| b.entity == entity)?;
+        let start = previous.bodies.get(index)?.position;
+        let end = self.bodies[index].position;
+        self.bodies
+            .iter()
+            .zip(&previous.bodies)
+            .enumerate()
+            .filter(|(other_index, (other, _))| *other_index != index && other.radius > 0.)
+            .filter_map(|(_, (other, before))| {
+                sweep_circle(start, end, before.position, other.position, other.radius)
+                    .map(|contact| (*other, contact))
+            })
+            .min_by(|a, b| a.1.time.total_cmp(&b.1.time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUN_MASS: f32 = 10000.;
+    const ORBIT_RADIUS: f32 = 200.;
+
+    /// A body on a circular orbit around a fixed sun at the origin.
+    fn orbit(integrator: Integrator, substeps: u32) -> GravityWorld {
+        let settings = GameplaySettings::default();
+        let speed = (settings.gravity * SUN_MASS / ORBIT_RADIUS).sqrt();
+        GravityWorld {
+            bodies: vec![
+                WorldBody::fixed(Entity::from_raw(0), Vec2::ZERO, SUN_MASS, 30.),
+                WorldBody::moving(
+                    Entity::from_raw(1),
+                    Vec2::new(ORBIT_RADIUS, 0.),
+                    Vec2::new(0., speed),
+                    1.,
+                    5.,
+                ),
+            ],
+            integration: Integration {
+                integrator,
+                substeps,
+            },
+            settings,
+            ..default()
+        }
+    }
+
+    /// The orbiting body's kinetic plus potential energy.
+    fn energy(world: &GravityWorld) -> f32 {
+        let body = world.bodies[1];
+        body.velocity.unwrap().length_squared() / 2.
+            - world.settings.gravity * SUN_MASS / body.position.length()
+    }
+
+    /// The largest relative change in energy over `ticks` fixed ticks.
+    fn energy_drift(mut world: GravityWorld, ticks: usize) -> f32 {
+        let initial = energy(&world);
+        (0..ticks)
+            .map(|_| {
+                world.step();
+                ((energy(&world) - initial) / initial).abs()
+            })
+            .fold(0., f32::max)
+    }
+
+    fn position_after(mut world: GravityWorld, ticks: usize) -> Vec2 {
+        for _ in 0..ticks {
+            world.step();
+        }
+        world.bodies[1].position
+    }
+
+    #[test]
+    fn verlet_and_rk4_keep_an_orbits_energy() {
+        let euler = energy_drift(orbit(Integrator::Euler, 1), 1500);
+        let verlet = energy_drift(orbit(Integrator::VelocityVerlet, 1), 1500);
+        let rk4 = energy_drift(orbit(Integrator::Rk4, 1), 1500);
+
+        assert!(verlet < 2e-5, "verlet drifted by {verlet}");
+        assert!(rk4 < 2e-5, "rk4 drifted by {rk4}");
+        assert!(euler > 10. * verlet, "euler drifted by only {euler}");
+    }
+
+    #[test]
+    fn integrators_agree_as_substeps_shrink() {
+        let reference = position_after(orbit(Integrator::Rk4, 32), 150);
+        let distance = |integrator, substeps| {
+            position_after(orbit(integrator, substeps), 150).distance(reference)
+        };
+
+        assert!(distance(Integrator::VelocityVerlet, 4) < 0.01);
+        assert!(distance(Integrator::Rk4, 4) < 0.01);
+        assert!(distance(Integrator::Euler, 16) < 0.5);
+        assert!(distance(Integrator::Euler, 16) < distance(Integrator::Euler, 1));
+    }
+
+    fn flying(entity: u32, position: Vec2, velocity: Vec2) -> WorldBody {
+        WorldBody::moving(Entity::from_raw(entity), position, velocity, 1., 5.)
+    }
+
+    #[test]
+    fn an_orbit_stays_round_with_the_default_integrator() {
+        let mut world = orbit(Integrator::default(), Integration::default().substeps);
+
+        for _ in 0..500 {
+            world.step();
+            let radius = world.bodies[1].position.length();
+            assert!(
+                (radius - ORBIT_RADIUS).abs() < 2.,
+                "drifted out to {radius}"
+            );
+        }
+    }
+
+    #[test]
+    fn first_contact_is_the_nearest_body_along_the_step() {
+        let player = Entity::from_raw(0);
+        let mut world = GravityWorld::new(vec![
+            flying(0, Vec2::ZERO, Vec2::new(300., 0.)),
+            WorldBody::fixed(Entity::from_raw(1), Vec2::new(16., 0.), 0., 3.),
+            WorldBody::fixed(Entity::from_raw(2), Vec2::new(10., 0.), 0., 3.),
+        ]);
+        let previous = world.clone();
+
+        world.step();
+        let (body, contact) = world.first_contact(&previous, player).unwrap();
+
+        assert_eq!(body.entity, Entity::from_raw(2));
+        assert!(contact.point.distance(Vec2::new(7., 0.)) < 1e-4);
+        assert!(contact.time > 0. && contact.time < 0.5);
+    }
+
+    #[test]
+    fn massless_pickups_are_touched_without_pulling() {
+        let player = Entity::from_raw(0);
+        let velocity = Vec2::new(300., 0.);
+        let mut world = GravityWorld::new(vec![
+            flying(0, Vec2::new(-20., 45.), velocity),
+            WorldBody::fixed(Entity::from_raw(1), Vec2::new(0., 50.), 0., 10.),
+        ]);
+        let previous = world.clone();
+
+        world.step();
+
+        assert_eq!(world.bodies[0].velocity, Some(velocity));
+        assert!(world.first_contact(&previous, player).is_some());
+        assert!(world.bodies[0].position.distance(Vec2::new(0., 45.)) < 1e-4);
+    }
+}
@@ -1,15 +1,139 @@
 use belly::prelude::*;
 use bevy::prelude::*;
 
-use crate::level::GoalStatus;
+use crate::{
+    effects::EffectRegistry,
+    inventory::{Inventory, EMPTY_SLOT_ICON},
+    level::{GoalStatus, GoalType, LevelEntity},
+    multiplayer::{player_name, Multiplayer},
+    player::Player,
+    score::Score,
+    thrust::Fuel,
+};
+
+/// What the in-game UI shows for the run as a whole.
+#[derive(Resource, Default, PartialEq)]
+pub struct Hud {
+    /// Each player's points, when there's more than one of them.
+    pub standings: String,
+}
+
+/// What the in-game UI shows for one player, gathered each frame.
+#[derive(Component, Default, PartialEq)]
+pub struct PlayerHud {
+    pub player: usize,
+    /// The player's name, when there's more than one of them.
+    pub name: String,
+    /// The player's current goal, as an image path.
+    pub goal: String,
+    pub fuel: String,
+    /// Icons for the player's inventory slots.
+    pub first_slot: String,
+    pub second_slot: String,
+    pub third_slot: String,
+}
 
 #[allow(clippy::clone_on_copy)]
-pub fn in_game_ui(mut commands: Commands) {
+pub fn in_game_ui(mut commands: Commands, multiplayer: Res<Multiplayer>) {
+    let huds = (0..multiplayer.players)
+        .map(|player| {
+            commands
+                .spawn((
+                    PlayerHud {
+                        player,
+                        ..default()
+                    },
+                    LevelEntity,
+                ))
+                .id()
+        })
+        .collect::<Vec<_>>();
     commands.add(eml! {
         <body>
             <div c:score-container>
-                <img bind:src=from!(GoalStatus:current.get_asset_string() | fmt.val("{val}"))/>
+                <div c:score>
+                    <label bind:value=from!(Score:points | fmt.val("Score: {val}"))/>
+                    <label c:standings bind:value=from!(Hud:standings | fmt.val("{val}"))/>
+                    <label c:award bind:value=from!(Score:last_award | fmt.val("{val}"))/>
+                </div>
+            </div>
+            <div c:inventory>
+                <for hud in=huds>
+                    <div c:player-hud>
+                        <label bind:value=from!(hud, PlayerHud:name | fmt.val("{val}"))/>
+                        <img c:goal bind:src=from!(hud, PlayerHud:goal | fmt.val("{val}"))/>
+                        <label c:fuel bind:value=from!(hud, PlayerHud:fuel | fmt.val("{val}"))/>
+                        <span c:slot>
+                            <img bind:src=from!(hud, PlayerHud:first_slot | fmt.val("{val}"))/>
+                            "1"
+                        </span>
+                        <span c:slot>
+                            <img bind:src=from!(hud, PlayerHud:second_slot | fmt.val("{val}"))/>
+                            "2"
+                        </span>
+                        <span c:slot>
+                            <img bind:src=from!(hud, PlayerHud:third_slot | fmt.val("{val}"))/>
+                            "3"
+                        </span>
+                    </div>
+                </for>
             </div>
         </body>
     });
 }
+
+pub(crate) fn update_hud(
+    mut hud: ResMut<Hud>,
+    mut player_huds: Query<&mut PlayerHud>,
+    goals: Res<GoalStatus>,
+    score: Res<Score>,
+    multiplayer: Res<Multiplayer>,
+    players: Query<(&Player, &Fuel, &Inventory)>,
+    registry: Res<EffectRegistry>,
+) {
+    let standings = if multiplayer.players > 1 {
+        score
+            .standings()
+            .into_iter()
+            .map(|(player, points)| format!("{} {points}", player_name(player)))
+            .collect::<Vec<_>>()
+            .join("  ")
+    } else {
+        String::new()
+    };
+    let next = Hud { standings };
+    if *hud != next {
+        *hud = next;
+    }
+
+    for mut player_hud in player_huds.iter_mut() {
+        let index = player_hud.player;
+        // Knocked out players keep their goal, with nothing left in their inventory.
+        let player = players.iter().find(|(player, ..)| player.0 == index);
+        let slots = player
+            .map(|(.., inventory)| [0, 1, 2].map(|slot| inventory.icon(slot, &registry)))
+            .unwrap_or([EMPTY_SLOT_ICON; 3]);
+        let goal = goals
+            .current
+            .get(index)
+            .unwrap_or(&GoalType::Chips)
+            .get_asset_string();
+
+        let next = PlayerHud {
+            player: index,
+            name: if multiplayer.players > 1 {
+                player_name(index)
+            } else {
+                String::new()
+            },
+            goal: goal.to_string(),
+            fuel: player.map(|(_, fuel, _)| fuel.label()).unwrap_or_default(),
+            first_slot: slots[0].to_string(),
+            second_slot: slots[1].to_string(),
+            third_slot: slots[2].to_string(),
+        };
+        if *player_hud != next {
+            *player_hud = next;
+        }
+    }
+}
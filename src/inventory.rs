@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+
+use crate::{
+    controls::{Action, Controls},
+    effects::{ActiveEffects, EffectKind, EffectRegistry},
+    level::LevelEvent,
+    multiplayer::Multiplayer,
+    pickup::PickupType,
+    player::Player,
+    replay::{PhysicsTick, ReplayMode, ReplayRecording},
+};
+
+pub const INVENTORY_SLOTS: usize = 3;
+pub const EMPTY_SLOT_ICON: &str = "empty_slot.png";
+
+/// Pickups a player has collected but not used yet. Using one starts its effect.
+#[derive(Component, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Inventory {
+    pub slots: [Option<EffectKind>; INVENTORY_SLOTS],
+}
+
+impl Inventory {
+    /// Puts the pickup in the first empty slot, if there is one.
+    pub fn store(&mut self, pickup: EffectKind) -> bool {
+        match self.slots.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(pickup);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn free_slots(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_none()).count()
+    }
+
+    /// Empties `slot`, returning the pickup that was in it.
+    pub fn take(&mut self, slot: usize) -> Option<EffectKind> {
+        self.slots.get_mut(slot)?.take()
+    }
+
+    pub fn icon(&self, slot: usize, registry: &EffectRegistry) -> &'static str {
+        self.slots
+            .get(slot)
+            .and_then(Option::as_ref)
+            .and_then(|pickup| registry.get(pickup.name()))
+            .map_or(EMPTY_SLOT_ICON, |rules| rules.icon)
+    }
+}
+
+/// Slots picked by each player, waiting for the next fixed tick to use them.
+#[derive(Resource, Default)]
+pub struct PendingActivations(pub Vec<(usize, usize)>);
+
+pub(crate) fn read_inventory_input(
+    controls: Controls,
+    replay_mode: Res<ReplayMode>,
+    multiplayer: Res<Multiplayer>,
+    players: Query<&Player, With<Inventory>>,
+    mut pending: ResMut<PendingActivations>,
+) {
+    if matches!(*replay_mode, ReplayMode::Replaying(_)) {
+        return;
+    }
+
+    for player in players.iter() {
+        let controls = controls.player(player.0, multiplayer.players);
+        for slot in 0..INVENTORY_SLOTS {
+            if controls.just_pressed(Action::UseSlot(slot)) {
+                pending.0.push((player.0, slot));
+            }
+        }
+    }
+}
+
+pub(crate) fn apply_activations(
+    mut pending: ResMut<PendingActivations>,
+    mut players: Query<(&Player, &mut Inventory, &mut ActiveEffects)>,
+    tick: Res<PhysicsTick>,
+    mut recording: ResMut<ReplayRecording>,
+    mut events: EventWriter<LevelEvent>,
+    registry: Res<EffectRegistry>,
+) {
+    for (index, slot) in pending.0.drain(..) {
+        for (player, mut inventory, mut effects) in players.iter_mut() {
+            if player.0 != index {
+                continue;
+            }
+            recording.0.activations.push((tick.0, index, slot));
+            if let Some(pickup) = inventory.take(slot) {
+                effects.start(pickup.name(), &registry);
+                events.send(LevelEvent::PickupActivated(
+                    PickupType::Effect(pickup),
+                    index,
+                ));
+            }
+        }
+    }
+}
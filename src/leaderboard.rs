@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    level::{GoalStatus, GoalType, LevelEvent, LevelSeed},
+    pickup::PickupType,
+    replay::PhysicsTick,
+    score::Score,
+    storage,
+};
+
+const LEADERBOARD_KEY: &str = "leaderboard";
+const MAX_HISTORY: usize = 50;
+pub const BEST_RUNS_SHOWN: usize = 3;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RunRecord {
+    pub errands: Vec<GoalType>,
+    pub time_survived: f32,
+    pub seed: u64,
+    pub pickups_used: Vec<PickupType>,
+    #[serde(default)]
+    pub score: u32,
+}
+
+impl RunRecord {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} points, {} errands in {:.0}s - seed {}",
+            self.score,
+            self.errands.len(),
+            self.time_survived,
+            self.seed
+        )
+    }
+}
+
+/// Every recent run, most recent last.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Leaderboard {
+    pub runs: Vec<RunRecord>,
+}
+
+impl Leaderboard {
+    pub fn load() -> Self {
+        storage::load(LEADERBOARD_KEY)
+    }
+
+    pub fn save(&self) {
+        storage::save(LEADERBOARD_KEY, self);
+    }
+
+    /// The best runs by score, then errands completed, with faster runs breaking ties.
+    pub fn best(&self, count: usize) -> Vec<&RunRecord> {
+        let mut runs = self.runs.iter().collect::<Vec<_>>();
+        runs.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then(b.errands.len().cmp(&a.errands.len()))
+                .then(a.time_survived.total_cmp(&b.time_survived))
+        });
+        runs.truncate(count);
+        runs
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct RunStats {
+    pub pickups_used: Vec<PickupType>,
+}
+
+pub(crate) fn reset_run_stats(mut commands: Commands) {
+    commands.insert_resource(RunStats::default());
+}
+
+pub(crate) fn track_run_stats(mut events: EventReader<LevelEvent>, mut stats: ResMut<RunStats>) {
+    for event in events.iter() {
+        // Held pickups only count once they're put to use, while fuel is used
+        // up as soon as it's collected.
+        match event {
+            LevelEvent::PickupCollected(PickupType::Fuel, _) => {
+                stats.pickups_used.push(PickupType::Fuel)
+            }
+            LevelEvent::PickupActivated(pickup, _) => stats.pickups_used.push(pickup.clone()),
+            _ => {}
+        }
+    }
+}
+
+pub(crate) fn record_run(
+    mut leaderboard: ResMut<Leaderboard>,
+    goals: Res<GoalStatus>,
+    tick: Res<PhysicsTick>,
+    seed: Res<LevelSeed>,
+    stats: Res<RunStats>,
+    score: Res<Score>,
+) {
+    leaderboard.runs.push(RunRecord {
+        errands: goals.completed.clone(),
+        time_survived: tick.time(),
+        seed: seed.0,
+        pickups_used: stats.pickups_used.clone(),
+        score: score.points,
+    });
+    if leaderboard.runs.len() > MAX_HISTORY {
+        let excess = leaderboard.runs.len() - MAX_HISTORY;
+        leaderboard.runs.drain(..excess);
+    }
+    leaderboard.save();
+}
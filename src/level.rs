@@ -1,16 +1,29 @@
 use crate::{
     assets::GameAssets,
+    campaign::{CampaignProgress, GameMode},
+    controls::VirtualCursor,
+    effects::{ActiveEffects, BoundaryResponse, EffectRegistry},
     game_state::GameState,
-    gravity::{self, DelayedActivity},
-    gravity_spawner::Prediction,
-    pickup::{self, ActivePickup, Pickup, PickupType, PICKUPS},
+    gameplay_settings::GameplaySettings,
+    gravity::{self, BlackHole, DelayedActivity},
+    gravity_spawner::{PendingPlacements, Prediction},
+    gravity_world::Integration,
+    inventory::{Inventory, PendingActivations},
+    level_definition::{ActiveLevel, CurrentLevel, GoalSequence, LevelDefinition, PlanetMotion},
+    multiplayer::{knock_out, player_color, Multiplayer},
+    pickup::{self, Pickup, PickupType},
+    planet_path::{PathCenter, PlanetPath},
     player,
+    replay::{PhysicsTick, Replay, ReplayRecording},
     space_material::SpaceMaterial,
+    spatial_index::SpatialGrid,
+    thrust::{Fuel, ThrustInput, FUEL_TINT},
 };
-use bevy::{math::Vec3Swizzles, prelude::*};
-use bevy_turborand::{DelegatedRng, GlobalRng};
+use bevy::prelude::*;
+use bevy_turborand::{DelegatedRng, GlobalRng, RngComponent};
 use iyes_loopless::state::NextState;
 use noisy_bevy::simplex_noise_2d;
+use serde::{Deserialize, Serialize};
 
 #[derive(Resource, Debug)]
 pub struct LevelBoundary {
@@ -21,21 +34,80 @@ pub struct LevelBoundary {
 #[derive(Component)]
 pub struct Backdrop;
 
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct LevelSeed(pub u64);
+
+/// A seed passed in with `--seed`, used for every run instead of a random one.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct RequestedSeed(pub Option<u64>);
+
+impl RequestedSeed {
+    pub fn from_args(args: impl Iterator<Item = String>) -> Self {
+        let mut args = args.skip_while(|arg| arg != "--seed").skip(1);
+        Self(args.next().and_then(|seed| seed.parse().ok()))
+    }
+}
+
+/// Separate streams for each spawner, so the order the spawn systems run in
+/// can't change which values each of them draws.
+#[derive(Resource)]
+pub struct LevelRng {
+    pub goals: RngComponent,
+    pub planets: RngComponent,
+    pub pickups: RngComponent,
+}
+
+impl LevelRng {
+    pub fn new(seed: LevelSeed) -> Self {
+        Self {
+            goals: RngComponent::with_seed(seed.0),
+            planets: RngComponent::with_seed(seed.0.wrapping_add(1)),
+            pickups: RngComponent::with_seed(seed.0.wrapping_add(2)),
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct LevelEntity;
 
+/// Set when a level starts and taken by the first physics tick, so the
+/// planets, goals and pickups are always spawned on that same tick.
+#[derive(Resource)]
+pub struct PendingLevelStart;
+
 pub enum LevelEvent {
     LevelStarted,
-    PickupCollected(PickupType),
+    /// A pickup was collected by the player with this index.
+    PickupCollected(PickupType, usize),
+    /// A pickup was taken out of that player's inventory and put to use.
+    PickupActivated(PickupType, usize),
 }
 
 #[derive(Resource)]
 pub struct GoalStatus {
-    pub current: GoalType,
+    /// Each player's current goal.
+    pub current: Vec<GoalType>,
+    /// Every goal delivered so far, by anyone.
     pub completed: Vec<GoalType>,
+    /// How many goals have been handed out, which is how far ordered goals have got.
+    pub handed_out: usize,
+}
+
+impl GoalStatus {
+    pub fn new(players: usize) -> Self {
+        Self {
+            current: vec![GoalType::Chips; players],
+            completed: vec![],
+            handed_out: 0,
+        }
+    }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+/// Only the player with this index can collect the goal it's on.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GoalOwner(pub usize);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum GoalType {
     Chips,
     Fruit,
@@ -45,6 +117,16 @@ pub enum GoalType {
 }
 
 impl GoalType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GoalType::Chips => "Chips",
+            GoalType::Fruit => "Fruit",
+            GoalType::Gas => "Gas",
+            GoalType::Post => "Post",
+            GoalType::ToiletPaper => "Toilet paper",
+        }
+    }
+
     pub fn get_asset_string(&self) -> &'static str {
         match self {
             GoalType::Chips => "chips.png",
@@ -56,30 +138,49 @@ impl GoalType {
     }
 }
 
-const GOAL_TYPES: [GoalType; 5] = [
-    GoalType::Chips,
-    GoalType::Fruit,
-    GoalType::Gas,
-    GoalType::Post,
-    GoalType::ToiletPaper,
-];
-
 pub(crate) fn check_boundary(
-    players: Query<&Transform, With<player::Player>>,
+    mut players: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut gravity::GravitationTransform,
+            &mut ActiveEffects,
+        ),
+        With<player::Player>,
+    >,
     boundary: Res<LevelBoundary>,
     mut commands: Commands,
-    active_pickup: Res<ActivePickup>,
+    multiplayer: Res<Multiplayer>,
+    registry: Res<EffectRegistry>,
 ) {
-    if matches!(active_pickup.0, Some(PickupType::Teleport)) {
-        return;
-    }
-    for player in players.iter() {
-        if player.translation.xy().cmplt(boundary.min).any()
-            || player.translation.xy().cmpgt(boundary.max).any()
+    for (entity, mut transform, mut gravitation_transform, mut effects) in players.iter_mut() {
+        let position = gravitation_transform.simulated_position(&transform);
+        if position.cmpge(boundary.min).all() && position.cmple(boundary.max).all() {
+            continue;
+        }
+
+        let velocity = match gravitation_transform.as_ref() {
+            gravity::GravitationTransform::Velocity { velocity, .. } => *velocity,
+            _ => Vec2::ZERO,
+        };
+        let mut exit = BoundaryResponse {
+            min: boundary.min,
+            max: boundary.max,
+            position,
+            velocity,
+            survived: false,
+        };
+        effects.leave_boundary(&registry, &mut exit);
+        if !exit.survived {
+            knock_out(&mut commands, &multiplayer, entity);
+            continue;
+        }
+        if let gravity::GravitationTransform::Velocity { velocity, .. } =
+            gravitation_transform.as_mut()
         {
-            commands.insert_resource(NextState(GameState::GameOver));
-            return;
+            *velocity = exit.velocity;
         }
+        gravity::move_player(&mut transform, &mut gravitation_transform, exit.position);
     }
 }
 
@@ -108,41 +209,157 @@ pub(crate) fn update_backdrop(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn start_level(
     mut commands: Commands,
     assets: Res<GameAssets>,
-    mut events: EventWriter<LevelEvent>,
+    requested_seed: Res<RequestedSeed>,
+    mut global_rng: ResMut<GlobalRng>,
+    definitions: Res<Assets<LevelDefinition>>,
+    mode: Res<GameMode>,
+    progress: Res<CampaignProgress>,
+    settings: Res<GameplaySettings>,
+    multiplayer: Res<Multiplayer>,
+    integration: Res<Integration>,
 ) {
+    let handle = match *mode {
+        GameMode::Campaign => assets.campaign.get(progress.current),
+        GameMode::Endless => None,
+    }
+    .unwrap_or(&assets.endless_level)
+    .clone();
+    let level = match definitions.get(&handle) {
+        Some(level) => level.clone(),
+        None => {
+            error!("Active level definition isn't loaded");
+            commands.insert_resource(NextState(GameState::Menu));
+            return;
+        }
+    };
+
+    let seed = LevelSeed(requested_seed.0.unwrap_or_else(|| global_rng.u64(..)));
+    commands.insert_resource(seed);
+    commands.insert_resource(LevelRng::new(seed));
+    commands.insert_resource(PhysicsTick::default());
+    commands.insert_resource(ReplayRecording(Replay::new(
+        seed.0,
+        *settings,
+        *multiplayer,
+        *integration,
+    )));
+    commands.insert_resource(PendingPlacements::default());
+    commands.insert_resource(PendingActivations::default());
     commands.insert_resource(LevelBoundary {
-        min: Vec2::new(-600., -400.),
-        max: Vec2::new(600., 400.),
-    });
-    events.send(LevelEvent::LevelStarted);
-    commands.insert_resource(Prediction::None);
-    commands.insert_resource(GoalStatus {
-        current: GoalType::Chips,
-        completed: vec![],
+        min: level.boundary.min,
+        max: level.boundary.max,
     });
-    commands.insert_resource(ActivePickup(None));
+    commands.insert_resource(PendingLevelStart);
+    commands.insert_resource(GoalStatus::new(multiplayer.players));
 
+    let start_positions = multiplayer.start_positions(level.player.position, level.player.velocity);
     commands
         .spawn((SpatialBundle::default(), LevelEntity))
         .with_children(|p| {
-            p.spawn((
-                SpriteBundle {
-                    sprite: Sprite {
-                        custom_size: Some(Vec2::ONE * 50.),
-                        ..Default::default()
+            for (index, position) in start_positions.into_iter().enumerate() {
+                p.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::ONE * 50.),
+                            color: player_color(index),
+                            ..Default::default()
+                        },
+                        texture: assets.player.clone(),
+                        transform: Transform::from_translation(position.extend(0.)),
+                        ..default()
                     },
-                    texture: assets.player.clone(),
-                    transform: Transform::from_translation(Vec3::new(-500., 0., 0.)),
-                    ..default()
-                },
-                gravity::GravitationalBody(1., 25.),
-                player::Player,
-                gravity::GravitationTransform::velocity(Vec2::X * 100.),
-            ));
+                    gravity::GravitationalBody(level.player.mass, level.player.radius),
+                    player::Player(index),
+                    gravity::GravitationTransform::velocity(level.player.velocity),
+                    ActiveEffects::default(),
+                    Inventory::default(),
+                    Prediction::None,
+                    Fuel::full(level.fuel),
+                    ThrustInput::default(),
+                    // Only the first player has a mouse to fall back on.
+                    VirtualCursor {
+                        position,
+                        active: index > 0,
+                    },
+                ));
+            }
         });
+
+    let mut planets = vec![];
+    for planet in level.planets.iter() {
+        let mut entity = commands.spawn((
+            SpriteBundle {
+                texture: if planet.black_hole {
+                    assets.hole.clone()
+                } else {
+                    assets.large_planet.clone()
+                },
+                transform: Transform::from_translation(planet.position.extend(0.)),
+                ..default()
+            },
+            gravity::GravitationalBody(planet.mass, planet.radius),
+            gravity::GravitationTransform::Static,
+            LevelEntity,
+        ));
+        if planet.black_hole {
+            entity.insert(BlackHole::default());
+        }
+        planets.push(entity.id());
+    }
+
+    // Orbits can refer to any planet in the list, so paths go on once they all exist.
+    for (planet, entity) in level.planets.iter().zip(planets.iter()) {
+        let path = match planet.motion {
+            PlanetMotion::Static => continue,
+            PlanetMotion::Orbit {
+                around: Some(index),
+                period,
+                ..
+            } => match (level.planets.get(index), planets.get(index)) {
+                (Some(center), Some(center_entity)) => PlanetPath::orbit_through(
+                    PathCenter::Body(*center_entity),
+                    center.position,
+                    planet.position,
+                    period,
+                    0.,
+                ),
+                _ => {
+                    warn!("Planet orbits a planet at index {index} that doesn't exist");
+                    continue;
+                }
+            },
+            PlanetMotion::Orbit {
+                around: None,
+                center,
+                period,
+            } => PlanetPath::orbit_through(
+                PathCenter::Point(center),
+                center,
+                planet.position,
+                period,
+                0.,
+            ),
+            PlanetMotion::Patrol { to, period } => {
+                PlanetPath::patrol(planet.position, to, period, 0.)
+            }
+            PlanetMotion::Free { velocity } => {
+                commands
+                    .entity(*entity)
+                    .insert(gravity::GravitationTransform::velocity(velocity));
+                continue;
+            }
+        };
+        commands.entity(*entity).insert(path);
+    }
+
+    commands.insert_resource(level.wells);
+    commands.insert_resource(level.preview);
+    commands.insert_resource(CurrentLevel(level));
+    commands.insert_resource(ActiveLevel(handle));
 }
 
 pub fn clear_level(
@@ -159,11 +376,10 @@ pub fn clear_level(
     }
 }
 
-const GOAL_GAP: f32 = 100.;
-const PLANET_GAP: f32 = 50.;
-
 const MAX_POSITION_TESTS: usize = 10;
 
+const NOISE_RANGE: f32 = 1000.;
+
 fn get_spawn_position<T: Fn(Vec2) -> bool>(
     rng_offset: f32,
     time: f32,
@@ -173,6 +389,7 @@ fn get_spawn_position<T: Fn(Vec2) -> bool>(
     check_valid: T,
 ) -> Vec2 {
     let bound_diff = bound_diff - gap * 2.;
+    let to_world = |noise: Vec2| noise.abs() * bound_diff + bound_min + gap;
     let mut offset = rng_offset;
     let mut position = Vec2::new(
         simplex_noise_2d(Vec2::new(time - offset * 15., (time + 5.) * offset * 5.)),
@@ -181,7 +398,7 @@ fn get_spawn_position<T: Fn(Vec2) -> bool>(
     let mut tests = 0;
 
     loop {
-        if check_valid(position) || tests >= MAX_POSITION_TESTS {
+        if check_valid(to_world(position)) || tests >= MAX_POSITION_TESTS {
             break;
         }
         tests += 1;
@@ -192,45 +409,88 @@ fn get_spawn_position<T: Fn(Vec2) -> bool>(
         offset = (position.x * position.y + position.y / 2.) * 1000.;
     }
 
-    position.abs() * bound_diff + bound_min + gap
+    to_world(position)
 }
 
+type SpacedBody<'a> = (
+    &'a Transform,
+    &'a gravity::GravitationTransform,
+    &'a gravity::GravitationalBody,
+);
+
+/// Indexes every body by the area around it that nothing new may spawn in.
+fn spacing_grid(existing_bodies: &Query<SpacedBody>, gap: f32) -> SpatialGrid<(Vec2, f32)> {
+    let mut grid = SpatialGrid::default();
+    for (transform, gravitation_transform, b) in existing_bodies.iter() {
+        // Spawns happen on the physics tick, so they go by where bodies are simulated.
+        let position = gravitation_transform.simulated_position(transform);
+        let clearance = b.1 + gap;
+        grid.insert_swept((position, clearance), position, position, clearance);
+    }
+    grid
+}
+
+fn is_clear(grid: &SpatialGrid<(Vec2, f32)>, p: Vec2) -> bool {
+    grid.query(p, p)
+        .all(|(position, clearance)| p.distance(*position) >= *clearance)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_goal(
     mut events: EventReader<LevelEvent>,
     mut commands: Commands,
     bounds: Res<LevelBoundary>,
-    existing_bodies: Query<(&GlobalTransform, &gravity::GravitationalBody)>,
-    time: Res<Time>,
+    existing_bodies: Query<SpacedBody>,
     assets: Res<GameAssets>,
-    mut rng: ResMut<GlobalRng>,
+    mut rng: ResMut<LevelRng>,
     mut goal_status: ResMut<GoalStatus>,
+    level: Res<CurrentLevel>,
+    settings: Res<GameplaySettings>,
 ) {
     if events.is_empty() {
         return;
     }
 
-    let time = time.elapsed_seconds();
     let offset = 670.;
     let bound_diff = bounds.max - bounds.min;
+    let grid = spacing_grid(&existing_bodies, settings.goal_gap);
+
+    let owners = events
+        .iter()
+        .flat_map(|event| match event {
+            LevelEvent::LevelStarted => (0..goal_status.current.len()).collect(),
+            LevelEvent::PickupCollected(PickupType::Goal, player) => vec![*player],
+            _ => vec![],
+        })
+        .collect::<Vec<_>>();
+
+    for owner in owners {
+        let time = rng.goals.f32() * NOISE_RANGE;
+        let position = get_spawn_position(
+            offset,
+            time,
+            bound_diff,
+            bounds.min,
+            settings.goal_gap,
+            |p| is_clear(&grid, p),
+        );
 
-    for event in events.iter() {
-        if !matches!(
-            event,
-            LevelEvent::PickupCollected(PickupType::Goal) | LevelEvent::LevelStarted
-        ) {
-            continue;
-        }
-        let position = get_spawn_position(offset, time, bound_diff, bounds.min, GOAL_GAP, |p| {
-            for (t, b) in existing_bodies.iter() {
-                if p.distance(t.translation().xy()) < b.1 + GOAL_GAP {
-                    return false;
-                }
+        let goal_type = match &level.0.goals {
+            GoalSequence::Random(goals) => rng.goals.sample(goals),
+            GoalSequence::Ordered(goals) if !goals.is_empty() => {
+                goals.get(goal_status.handed_out % goals.len())
             }
-            true
-        });
-
-        let goal_type = rng.sample(&GOAL_TYPES).unwrap();
-        goal_status.current = *goal_type;
+            GoalSequence::Ordered(_) => None,
+            GoalSequence::ShoppingList(goals) => goals.get(goal_status.handed_out),
+        };
+        let goal_type = match goal_type {
+            Some(goal_type) => *goal_type,
+            None => continue,
+        };
+        goal_status.handed_out += 1;
+        if let Some(current) = goal_status.current.get_mut(owner) {
+            *current = goal_type;
+        }
         let asset = match goal_type {
             GoalType::Chips => &assets.chips,
             GoalType::Fruit => &assets.fruit,
@@ -244,6 +504,7 @@ pub fn spawn_goal(
             SpriteBundle {
                 sprite: Sprite {
                     custom_size: Some(Vec2::ONE * 50.),
+                    color: player_color(owner),
                     ..Default::default()
                 },
                 texture: asset,
@@ -251,115 +512,182 @@ pub fn spawn_goal(
                 ..default()
             },
             pickup::Pickup(30., pickup::PickupType::Goal),
+            GoalOwner(owner),
             LevelEntity,
         ));
-        break;
     }
 }
 
+const MIN_PLANET_PERIOD: f32 = 10.;
+const MAX_PLANET_PERIOD: f32 = 20.;
+
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_planet(
     mut events: EventReader<LevelEvent>,
     mut commands: Commands,
     bounds: Res<LevelBoundary>,
-    existing_bodies: Query<(&GlobalTransform, &gravity::GravitationalBody)>,
-    time: Res<Time>,
+    existing_bodies: Query<SpacedBody>,
     assets: Res<GameAssets>,
+    mut rng: ResMut<LevelRng>,
+    level: Res<CurrentLevel>,
+    tick: Res<PhysicsTick>,
+    settings: Res<GameplaySettings>,
 ) {
-    if events.is_empty() {
+    if events.is_empty() || !level.0.generate_planets {
         return;
     }
 
-    let time = time.elapsed_seconds();
     let offset = 58.;
     let bound_diff = bounds.max - bounds.min;
+    let grid = spacing_grid(&existing_bodies, settings.planet_gap);
 
     for event in events.iter() {
         if !matches!(
             event,
-            LevelEvent::PickupCollected(PickupType::Goal) | LevelEvent::LevelStarted
+            LevelEvent::PickupCollected(PickupType::Goal, _) | LevelEvent::LevelStarted
         ) {
             continue;
         }
-        let position = get_spawn_position(offset, time, bound_diff, bounds.min, 0., |p| {
-            for (t, b) in existing_bodies.iter() {
-                if p.distance(t.translation().xy()) < b.1 + PLANET_GAP {
-                    return false;
-                }
-            }
-            true
-        });
-
-        commands.spawn((
+        let is_valid = |p: Vec2| is_clear(&grid, p);
+        let time = rng.planets.f32() * NOISE_RANGE;
+        let position = get_spawn_position(offset, time, bound_diff, bounds.min, 0., is_valid);
+
+        let is_black_hole = rng.planets.f32() < level.0.black_hole_chance;
+        let (body, texture) = if is_black_hole {
+            (BlackHole::body(), assets.hole.clone())
+        } else {
+            (
+                gravity::GravitationalBody(settings.planet_mass, 30.),
+                assets.large_planet.clone(),
+            )
+        };
+
+        let mut planet = commands.spawn((
             SpriteBundle {
                 sprite: Sprite {
                     custom_size: Some(Vec2::ONE * 50.),
                     ..Default::default()
                 },
-                texture: assets.large_planet.clone(),
+                texture,
                 transform: Transform::from_translation(Vec3::new(position.x, position.y, 0.)),
                 ..default()
             },
-            gravity::GravitationalBody(10000., 30.),
+            body,
             gravity::GravitationTransform::Static,
             LevelEntity,
-            DelayedActivity(3.),
+            DelayedActivity(settings.grace_period),
         ));
+        if is_black_hole {
+            planet.insert(BlackHole::default());
+        }
+
+        if rng.planets.f32() < level.0.moving_planet_chance {
+            let period =
+                MIN_PLANET_PERIOD + rng.planets.f32() * (MAX_PLANET_PERIOD - MIN_PLANET_PERIOD);
+            let started_at = tick.time() + settings.grace_period;
+            let path = if rng.planets.bool() {
+                let center = (bounds.min + bounds.max) / 2.;
+                PlanetPath::orbit_through(
+                    PathCenter::Point(center),
+                    center,
+                    position,
+                    period,
+                    started_at,
+                )
+            } else {
+                let time = rng.planets.f32() * NOISE_RANGE;
+                let to = get_spawn_position(offset, time, bound_diff, bounds.min, 0., is_valid);
+                PlanetPath::patrol(position, to, period, started_at)
+            };
+            planet.insert(path);
+        }
         break;
     }
 }
 
-const PICKUP_PROBABILITY: f32 = 0.1;
+/// Announces the level on the physics tick, where the spawners will read it.
+pub fn announce_level_start(
+    mut commands: Commands,
+    pending: Option<Res<PendingLevelStart>>,
+    mut events: EventWriter<LevelEvent>,
+) {
+    if pending.is_some() {
+        commands.remove_resource::<PendingLevelStart>();
+        events.send(LevelEvent::LevelStarted);
+    }
+}
 
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_pickup(
     mut commands: Commands,
-    event: EventReader<LevelEvent>,
+    mut events: EventReader<LevelEvent>,
     assets: Res<GameAssets>,
-    time: Res<Time>,
     bounds: Res<LevelBoundary>,
-    existing_bodies: Query<(&GlobalTransform, &gravity::GravitationalBody)>,
-    mut rng: ResMut<GlobalRng>,
+    existing_bodies: Query<SpacedBody>,
+    mut rng: ResMut<LevelRng>,
+    level: Res<CurrentLevel>,
+    settings: Res<GameplaySettings>,
+    registry: Res<EffectRegistry>,
 ) {
-    if event.is_empty() {
+    if events.is_empty() {
         return;
     }
-    let time = time.elapsed_seconds();
-    let offset = 162.;
-    let bound_diff = bounds.max - bounds.min;
-    let position = get_spawn_position(offset, time, bound_diff, bounds.min, 0., |p| {
-        for (t, b) in existing_bodies.iter() {
-            if p.distance(t.translation().xy()) < b.1 + PLANET_GAP {
-                return false;
-            }
-        }
-        true
-    });
+    let grid = spacing_grid(&existing_bodies, settings.planet_gap);
 
-    let probability = rng.f32_normalized().abs();
-    if probability.abs() > PICKUP_PROBABILITY {
-        return;
-    }
-    let pickup = *rng.sample(&PICKUPS).unwrap();
+    for event in events.iter() {
+        if !matches!(
+            event,
+            LevelEvent::PickupCollected(PickupType::Goal, _) | LevelEvent::LevelStarted
+        ) {
+            continue;
+        }
+        let time = rng.pickups.f32() * NOISE_RANGE;
+        let offset = 162.;
+        let bound_diff = bounds.max - bounds.min;
+        let position = get_spawn_position(offset, time, bound_diff, bounds.min, 0., |p| {
+            is_clear(&grid, p)
+        });
 
-    let (pickup, image) = match pickup {
-        PickupType::PlanetKiller => (
-            Pickup(30., PickupType::PlanetKiller),
-            assets.planet_killer_pickup.clone(),
-        ),
-        PickupType::Goal => (Pickup(30., PickupType::Goal), assets.goal.clone()),
-        PickupType::Teleport => (Pickup(30., PickupType::Teleport), assets.teleport.clone()),
-    };
+        let probability = rng.pickups.f32_normalized().abs();
+        if probability.abs() > level.0.pickups.probability * settings.pickup_chance {
+            continue;
+        }
+        let pickup = match rng.pickups.sample(&level.0.pickups.pickups) {
+            Some(pickup) => pickup.clone(),
+            None => continue,
+        };
+
+        // Pickups that start an effect look however the effect says they should.
+        let (image, color) = match &pickup {
+            PickupType::Goal => (assets.goal.clone(), Color::WHITE),
+            PickupType::Fuel => (assets.gas.clone(), FUEL_TINT),
+            PickupType::Effect(kind) => {
+                let rules = registry.get(kind.name());
+                (
+                    rules
+                        .and_then(|rules| rules.pickup_image)
+                        .map_or_else(|| assets.goal.clone(), |image| image(&assets)),
+                    rules
+                        .and_then(|rules| rules.pickup_tint)
+                        .unwrap_or(Color::WHITE),
+                )
+            }
+        };
+        let pickup = Pickup(30., pickup);
 
-    commands.spawn((
-        SpriteBundle {
-            sprite: Sprite {
-                custom_size: Some(Vec2::ONE * pickup.0 * 2.),
-                ..Default::default()
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::ONE * pickup.0 * 2.),
+                    color,
+                    ..Default::default()
+                },
+                texture: image,
+                transform: Transform::from_translation(Vec3::new(position.x, position.y, 0.)),
+                ..default()
             },
-            texture: image,
-            transform: Transform::from_translation(Vec3::new(position.x, position.y, 0.)),
-            ..default()
-        },
-        pickup,
-        crate::level::LevelEntity,
-    ));
+            pickup,
+            crate::level::LevelEntity,
+        ));
+    }
 }
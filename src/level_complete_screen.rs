@@ -0,0 +1,64 @@
+use belly::prelude::*;
+use bevy::prelude::*;
+
+use crate::{
+    campaign::CampaignProgress,
+    level::GoalStatus,
+    menu_navigation::{play, to_menu, MenuFocus},
+    score::Score,
+};
+
+#[allow(clippy::clone_on_copy)]
+pub fn setup_level_complete(
+    mut commands: Commands,
+    goals: Res<GoalStatus>,
+    progress: Res<CampaignProgress>,
+    score: Res<Score>,
+) {
+    let goals = goals
+        .completed
+        .iter()
+        .map(|g| g.get_asset_string())
+        .collect::<Vec<_>>();
+    let title = if progress.finished {
+        "Campaign Complete!".to_string()
+    } else {
+        format!("Level {} unlocked", progress.current + 1)
+    };
+    let total = format!("Score: {}", score.points);
+    commands.insert_resource(MenuFocus::new(vec![
+        ("focus-play", play),
+        ("focus-menu", to_menu),
+    ]));
+    commands.add(eml! {
+        <body>
+            <div c:modal>
+                <div c:modal_content>
+                <div c:subheader>
+                    {title}
+                </div>
+                <div c:header>
+                    <for goal in = goals>
+                        <img src=goal/>
+                    </for>
+                </div>
+                <div c:score>
+                    {total}
+                </div>
+                <div>
+                    <button c:focus-play on:press=connect!(|ctx| play(ctx.commands()))>
+                        <span c:content>
+                            <img src="paper.png"/>
+                        </span>
+                    </button>
+                    <button c:focus-menu on:press=connect!(|ctx| to_menu(ctx.commands()))>
+                        <span c:content>
+                            <img src="credits.png"/>
+                        </span>
+                    </button>
+                </div>
+                </div>
+            </div>
+        </body>
+    });
+}
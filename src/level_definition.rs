@@ -0,0 +1,254 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use iyes_loopless::state::{CurrentState, NextState};
+use serde::Deserialize;
+
+use crate::{
+    effects::{
+        black_hole, inverter, magnet, planet_killer, shield, slow_motion, teleport, EffectKind,
+    },
+    game_state::GameState,
+    gravity_spawner::{TrajectorySettings, WellRules},
+    level::GoalType,
+    pickup::PickupType,
+};
+
+/// A hand-authored level, loaded from a `.level.ron` file in the assets folder.
+#[derive(Deserialize, TypeUuid, Debug, Clone)]
+#[uuid = "4b7a0a57-43d1-4c5b-9a63-1f2e8e0c6d11"]
+pub struct LevelDefinition {
+    pub boundary: BoundaryDefinition,
+    pub player: PlayerDefinition,
+    #[serde(default)]
+    pub planets: Vec<PlanetDefinition>,
+    #[serde(default = "default_generate_planets")]
+    pub generate_planets: bool,
+    /// The chance that a generated planet is a black hole instead.
+    #[serde(default)]
+    pub black_hole_chance: f32,
+    /// The chance that a generated planet orbits the middle of the level or patrols.
+    #[serde(default)]
+    pub moving_planet_chance: f32,
+    #[serde(default)]
+    pub goals: GoalSequence,
+    #[serde(default)]
+    pub pickups: PickupTable,
+    #[serde(default)]
+    pub wells: WellRules,
+    #[serde(default)]
+    pub preview: TrajectorySettings,
+    /// Seconds of full thrust the player starts with. Without any, thrust is disabled.
+    #[serde(default)]
+    pub fuel: f32,
+}
+
+impl LevelDefinition {
+    /// Turns planets whose motion can't be simulated, like a path with no
+    /// period, back into static ones.
+    pub fn validate(&mut self) {
+        for (index, planet) in self.planets.iter_mut().enumerate() {
+            if let PlanetMotion::Orbit { period, .. } | PlanetMotion::Patrol { period, .. } =
+                planet.motion
+            {
+                if period.is_nan() || period <= 0. {
+                    warn!("Planet {index} has a period of {period}, so it will stay still");
+                    planet.motion = PlanetMotion::Static;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BoundaryDefinition {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PlayerDefinition {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub mass: f32,
+    pub radius: f32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PlanetDefinition {
+    pub position: Vec2,
+    pub mass: f32,
+    pub radius: f32,
+    #[serde(default)]
+    pub black_hole: bool,
+    #[serde(default)]
+    pub motion: PlanetMotion,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub enum PlanetMotion {
+    #[default]
+    Static,
+    /// Circles `center`, or the planet at index `around` in this level's list,
+    /// passing through the planet's position.
+    Orbit {
+        #[serde(default)]
+        around: Option<usize>,
+        #[serde(default)]
+        center: Vec2,
+        period: f32,
+    },
+    /// Moves to `to` and back again once per period.
+    Patrol { to: Vec2, period: f32 },
+    /// Starts with this velocity and is pulled by every other body.
+    Free { velocity: Vec2 },
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub enum GoalSequence {
+    /// Each goal is picked at random from the list.
+    Random(Vec<GoalType>),
+    /// Goals are handed out in order, starting over once the list runs out.
+    Ordered(Vec<GoalType>),
+    /// Goals are handed out in order, and the level is complete once they're all collected.
+    ShoppingList(Vec<GoalType>),
+}
+
+impl Default for GoalSequence {
+    fn default() -> Self {
+        Self::Random(vec![
+            GoalType::Chips,
+            GoalType::Fruit,
+            GoalType::Gas,
+            GoalType::Post,
+            GoalType::ToiletPaper,
+        ])
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PickupTable {
+    pub probability: f32,
+    pub pickups: Vec<PickupType>,
+}
+
+impl Default for PickupTable {
+    fn default() -> Self {
+        Self {
+            probability: 0.1,
+            pickups: vec![
+                PickupType::Effect(EffectKind::new(teleport::TELEPORT)),
+                PickupType::Effect(EffectKind::new(planet_killer::PLANET_KILLER)),
+                PickupType::Effect(EffectKind::new(inverter::INVERTER)),
+                PickupType::Effect(EffectKind::new(black_hole::BLACK_HOLE)),
+                PickupType::Effect(EffectKind::new(shield::SHIELD)),
+                PickupType::Effect(EffectKind::new(slow_motion::SLOW_MOTION)),
+                PickupType::Effect(EffectKind::new(magnet::MAGNET)),
+                PickupType::Fuel,
+            ],
+        }
+    }
+}
+
+fn default_generate_planets() -> bool {
+    true
+}
+
+#[derive(Default)]
+pub struct LevelDefinitionLoader;
+
+impl AssetLoader for LevelDefinitionLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let mut level = ron::de::from_bytes::<LevelDefinition>(bytes)?;
+            level.validate();
+            load_context.set_default_asset(LoadedAsset::new(level));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+/// The level that the next run will be built from.
+#[derive(Resource, Clone)]
+pub struct ActiveLevel(pub Handle<LevelDefinition>);
+
+/// A copy of the active definition taken when the level started.
+#[derive(Resource, Clone)]
+pub struct CurrentLevel(pub LevelDefinition);
+
+pub struct LevelDefinitionPlugin;
+
+impl Plugin for LevelDefinitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<LevelDefinition>()
+            .init_asset_loader::<LevelDefinitionLoader>()
+            .add_system(reload_level);
+    }
+}
+
+fn reload_level(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<LevelDefinition>>,
+    active: Option<Res<ActiveLevel>>,
+    state: Res<CurrentState<GameState>>,
+) {
+    let active = match active {
+        Some(active) => active,
+        None => return,
+    };
+    for event in events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            if *handle == active.0 && state.0 == GameState::Playing {
+                commands.insert_resource(NextState(GameState::Playing));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level_with_motions(motions: &str) -> LevelDefinition {
+        let planets = motions
+            .split(';')
+            .map(|motion| {
+                format!("(position: (100., 0.), mass: 1000., radius: 20., motion: {motion})")
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        ron::from_str(&format!(
+            "(boundary: (min: (-500., -300.), max: (500., 300.)), \
+             player: (position: (0., 0.), velocity: (10., 0.), mass: 1., radius: 5.), \
+             planets: [{planets}])"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn paths_without_a_positive_period_stay_still() {
+        let mut level = level_with_motions(
+            "Orbit(period: 0.); Patrol(to: (0., 100.), period: -2.); Orbit(center: (0., 0.), period: 5.)",
+        );
+
+        level.validate();
+
+        assert!(matches!(level.planets[0].motion, PlanetMotion::Static));
+        assert!(matches!(level.planets[1].motion, PlanetMotion::Static));
+        assert!(matches!(
+            level.planets[2].motion,
+            PlanetMotion::Orbit { period, .. } if period == 5.
+        ));
+    }
+}
@@ -1,19 +1,37 @@
 mod assets;
 mod audio;
+mod campaign;
+mod controls;
 mod credits_screen;
+mod effects;
 mod game_menu_screen;
 mod game_over_screen;
 mod game_state;
+mod gameplay_settings;
 mod gravity;
 mod gravity_spawner;
+mod gravity_world;
 mod in_game_ui;
+mod inventory;
+mod leaderboard;
 mod level;
+mod level_complete_screen;
+mod level_definition;
 mod main_camera;
+mod menu_navigation;
+mod multiplayer;
 mod pickup;
+mod planet_path;
 mod player;
+mod replay;
+mod score;
+mod simulation;
 mod space_material;
-
-use std::time::Duration;
+mod spatial_index;
+mod sweep;
+mod thrust;
+mod time_scale;
+mod touch;
 
 use assets::{GameAssets, GameLoadState};
 use audio::{BackgroundMusic, ForegroundAudio};
@@ -30,17 +48,16 @@ use credits_screen::setup_credits;
 use game_menu_screen::setup_menu;
 use game_over_screen::setup_game_over;
 use game_state::GameState;
-use gravity::FIXED_TIME_MILIS;
-use gravity_spawner::{Prediction, TrajectoryPoint};
 use iyes_loopless::{
     prelude::{AppLooplessFixedTimestepExt, AppLooplessStateExt, ConditionSet},
     state::NextState,
 };
-use level::LevelEvent;
-use level::{Backdrop, GoalStatus, GoalType};
+use level::Backdrop;
+use level_complete_screen::setup_level_complete;
+use level_definition::LevelDefinitionPlugin;
 use noisy_bevy::NoisyShaderPlugin;
-use pickup::ActivePickup;
 use player::{player_has_pickup_modifiers, set_player_image};
+use simulation::SimulationPlugin;
 use space_material::SpaceMaterial;
 
 fn main() {
@@ -74,35 +91,39 @@ fn main() {
         .add_plugin(Material2dPlugin::<space_material::SpaceMaterial>::default())
         .add_plugin(NoisyShaderPlugin)
         .add_plugin(RngPlugin::default())
+        .add_plugin(LevelDefinitionPlugin)
         .add_plugin(AudioPlugin)
         .add_audio_channel::<BackgroundMusic>()
         .add_audio_channel::<ForegroundAudio>();
 
-    app.add_event::<LevelEvent>()
-        .insert_resource(level::LevelBoundary {
-            min: Vec2::new(-500., -300.),
-            max: Vec2::new(500., 300.),
-        })
-        .insert_resource(GoalStatus {
-            current: GoalType::Chips,
-            completed: vec![],
-        })
-        .insert_resource(Prediction::None)
-        .insert_resource(ActivePickup(None))
-        .add_loopless_state(GameState::Loading)
-        .add_startup_system(setup)
+    app.add_plugin(SimulationPlugin)
+        .insert_resource(level::RequestedSeed::from_args(std::env::args()))
+        .insert_resource(gravity_world::Integration::from_args(std::env::args()))
+        .init_resource::<campaign::GameMode>()
+        .insert_resource(campaign::CampaignProgress::load())
+        .insert_resource(leaderboard::Leaderboard::load())
+        .init_resource::<leaderboard::RunStats>()
+        .init_resource::<gravity_spawner::TrajectorySettings>()
+        .init_resource::<controls::InputMap>()
+        .init_resource::<touch::TouchPointer>()
+        .init_resource::<in_game_ui::Hud>()
+        .init_resource::<game_menu_screen::MenuLabels>()
+        .insert_resource(gameplay_settings::GameplaySettings::load());
+
+    #[cfg(not(target_arch = "wasm32"))]
+    configure_replay(&mut app);
+
+    app.add_startup_system(setup)
         .add_enter_system(GameLoadState::Ready, loaded)
         .add_enter_system(GameState::Playing, level::start_level)
         .add_enter_system(GameState::Playing, in_game_ui::in_game_ui)
+        .add_enter_system(GameState::Playing, leaderboard::reset_run_stats)
         .add_exit_system(GameState::Playing, level::clear_level)
-        .add_fixed_timestep(Duration::from_millis(FIXED_TIME_MILIS), "calculate_physics")
-        .add_fixed_timestep_system_set(
-            "calculate_physics",
-            0,
+        .add_system_set(
             ConditionSet::new()
+                .before("planet_spawn")
                 .run_in_state(GameState::Playing)
-                .with_system(gravity::calculate_gravity)
-                .with_system(gravity::adjust_rotation)
+                .with_system(touch::track_touches)
                 .into(),
         )
         .add_system_set(
@@ -110,42 +131,111 @@ fn main() {
                 .label("planet_spawn")
                 .run_in_state(GameState::Playing)
                 .with_system(main_camera::position_main_camera)
-                .with_system(pickup::check_pickup)
+                .with_system(controls::move_virtual_cursor)
+                .with_system(controls::show_virtual_cursor)
                 .with_system(gravity_spawner::gravity_spawner)
-                .with_system(level::check_boundary)
+                .with_system(thrust::read_thrust_input)
+                .with_system(inventory::read_inventory_input)
                 .with_system(level::update_backdrop)
-                .with_system(gravity::smooth_movement)
+                .with_system(gravity::sync_trajectory_points)
                 .with_system(gravity::predict_trajectory)
-                .with_system(gravity::check_crash)
                 .with_system(gravity::set_sprite_to_radius)
-                .with_system(level::spawn_planet)
+                .with_system(gravity_spawner::dress_placed_wells)
                 .with_system(gravity::delayed_activity_flasher)
                 .with_system(set_player_image)
                 .with_system(player_has_pickup_modifiers)
+                .with_system(leaderboard::track_run_stats)
+                .with_system(in_game_ui::update_hud)
                 .into(),
         )
-        .add_system_set(
-            ConditionSet::new()
-                .run_in_state(GameState::Playing)
-                .after("planet_spawn")
+        .add_fixed_timestep_child_stage("calculate_physics")
+        .add_fixed_timestep_system_set(
+            "calculate_physics",
+            3,
+            simulation::physics_tick_set()
+                .with_system(level::spawn_planet)
                 .with_system(level::spawn_goal)
                 .with_system(level::spawn_pickup)
+                .with_system(campaign::check_level_complete)
+                .into(),
+        )
+        .add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameLoadState::Ready)
+                .after("simulation")
+                .with_system(audio::play_event_audio)
+                .into(),
+        )
+        .add_system(menu_navigation::navigate_menu)
+        .add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::Menu)
+                .with_system(game_menu_screen::update_menu_labels)
                 .into(),
         )
         .add_enter_system(GameState::Menu, setup_menu)
-        .add_enter_system(GameState::GameOver, setup_game_over)
+        .add_enter_system(GameState::GameOver, leaderboard::record_run)
+        .add_enter_system(
+            GameState::GameOver,
+            setup_game_over.after(leaderboard::record_run),
+        )
+        .add_enter_system(GameState::LevelComplete, campaign::complete_level)
+        .add_enter_system(GameState::LevelComplete, leaderboard::record_run)
+        .add_enter_system(
+            GameState::LevelComplete,
+            setup_level_complete.after(campaign::complete_level),
+        )
         .add_enter_system(GameState::Credits, setup_credits)
         .add_exit_system(GameState::Menu, clear_ui)
         .add_exit_system(GameState::Credits, clear_ui)
         .add_exit_system(GameState::Playing, clear_ui)
-        .add_exit_system(GameState::GameOver, clear_ui);
+        .add_exit_system(GameState::GameOver, clear_ui)
+        .add_exit_system(GameState::LevelComplete, clear_ui)
+        .add_exit_system(GameState::Menu, menu_navigation::clear_menu_focus)
+        .add_exit_system(GameState::Credits, menu_navigation::clear_menu_focus)
+        .add_exit_system(GameState::GameOver, menu_navigation::clear_menu_focus)
+        .add_exit_system(GameState::LevelComplete, menu_navigation::clear_menu_focus);
 
     #[cfg(profile = "dev")]
     app.add_system(gravity_bounding_visualizer);
 
+    #[cfg(debug_assertions)]
+    app.add_system(time_scale::debug_time_controls);
+
     app.run();
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn configure_replay(app: &mut App) {
+    let args = std::env::args().collect::<Vec<_>>();
+    let arg_value = |name: &str| args.iter().skip_while(|arg| *arg != name).nth(1).cloned();
+
+    if let Some(path) = arg_value("--record") {
+        app.insert_resource(replay::ReplayOutput(Some(path.into())));
+    }
+
+    if let Some(path) = arg_value("--replay") {
+        match replay::ReplayMode::load(&path, app.world.resource::<effects::EffectRegistry>()) {
+            Ok(mode) => {
+                if let replay::ReplayMode::Replaying(replay) = &mode {
+                    app.insert_resource(level::RequestedSeed(Some(replay.seed)));
+                    if let Some(settings) = replay.settings {
+                        app.insert_resource(settings);
+                    }
+                    if let Some(multiplayer) = replay.multiplayer {
+                        app.insert_resource(multiplayer);
+                    }
+                    if let Some(integration) = replay.integration {
+                        app.insert_resource(integration);
+                    }
+                }
+                app.insert_resource(mode);
+            }
+            Err(e) => error!("Couldn't load replay {path}: {e}"),
+        }
+    }
+}
+
 fn loaded(
     mut commands: Commands,
     audio: Res<AudioChannel<BackgroundMusic>>,
@@ -166,7 +256,6 @@ fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut space_materials: ResMut<Assets<space_material::SpaceMaterial>>,
-    mut color_materials: ResMut<Assets<ColorMaterial>>,
 ) {
     commands.add(StyleSheet::load("ui-style.ess"));
     commands
@@ -180,6 +269,7 @@ fn setup(
             },
             main_camera::MainCamera,
             main_camera::ElasticCentering(1., Vec2::ZERO),
+            main_camera::CameraZoom::default(),
             ComputedVisibility::default(),
             Visibility::default(),
         ))
@@ -196,17 +286,4 @@ fn setup(
                 Backdrop,
             ));
         });
-
-    for _ in 0..10 {
-        commands.spawn((
-            MaterialMesh2dBundle {
-                mesh: meshes.add(shape::RegularPolygon::new(3., 8).into()).into(),
-                material: color_materials.add(Color::WHITE.into()),
-                transform: Transform::default(),
-                visibility: Visibility::INVISIBLE,
-                ..default()
-            },
-            TrajectoryPoint,
-        ));
-    }
 }
@@ -14,16 +14,46 @@ pub(crate) const VELOCITY_OFFSET_MIN: Vec2 = Vec2::splat(-300.);
 
 const EDGE_DISPLAY_BUFFER: f32 = 50.;
 
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 2.5;
+/// How quickly the zoom catches up with a pinch, per second.
+const ZOOM_EASING: f32 = 8.;
+
 #[derive(Component)]
 pub struct MainCamera;
 
 #[derive(Component)]
 pub struct ElasticCentering(pub f32, pub Vec2);
 
+/// Scales the elastic zoom on top of fitting the players in view. Pinching
+/// moves the target, and the zoom eases towards it.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct CameraZoom {
+    pub current: f32,
+    pub target: f32,
+}
+
+impl Default for CameraZoom {
+    fn default() -> Self {
+        Self {
+            current: 1.,
+            target: 1.,
+        }
+    }
+}
+
+impl CameraZoom {
+    /// Zooms in as the fingers spread out, by the ratio of their distance to the last frame's.
+    pub fn pinch(&mut self, ratio: f32) {
+        self.target = (self.target * ratio).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+}
+
 pub(crate) type ElasticCamera<'a> = (
     &'a mut Transform,
     &'a mut OrthographicProjection,
     &'a mut ElasticCentering,
+    &'a mut CameraZoom,
 );
 
 pub(crate) fn position_main_camera(
@@ -39,12 +69,12 @@ pub(crate) fn position_main_camera(
         num_players += 1.;
         let pos = player.translation.xy();
         let offset = match velocity {
-            gravity::GravitationTransform::Static => Vec2::ZERO,
             gravity::GravitationTransform::Velocity {
                 velocity,
                 start_position: _,
                 target_position: _,
             } => *velocity * VELOCITY_RATIO,
+            _ => Vec2::ZERO,
         };
         let offset = offset.min(VELOCITY_OFFSET_MAX).max(VELOCITY_OFFSET_MIN);
         target_offset += offset;
@@ -67,7 +97,7 @@ pub(crate) fn position_main_camera(
         bounds.max + EDGE_DISPLAY_BUFFER,
     );
 
-    for (mut transform, mut projection, mut centering) in camera.iter_mut() {
+    for (mut transform, mut projection, mut centering, mut zoom) in camera.iter_mut() {
         let camera_center = transform.translation.xy();
 
         let (gap, pos) = if let Some((min, max)) = player_bounds {
@@ -78,7 +108,8 @@ pub(crate) fn position_main_camera(
         };
 
         let max_component = gap.max_element();
-        let horizontal = BUFFER + max_component;
+        zoom.current += (zoom.target - zoom.current) * (ZOOM_EASING * delta).min(1.);
+        let horizontal = (BUFFER + max_component) / zoom.current;
 
         let offset = (target_offset * delta + centering.1 * (centering.0 - delta)) / centering.0;
         centering.1 = offset;
@@ -0,0 +1,71 @@
+use belly::prelude::*;
+use bevy::prelude::*;
+use iyes_loopless::state::NextState;
+
+use crate::{
+    controls::{Action, Controls},
+    game_state::GameState,
+};
+
+pub type MenuAction = fn(&mut Commands);
+
+/// The buttons on the current screen, in the order that focus moves through
+/// them. Each one is a class the button is tagged with, so it can be
+/// highlighted, and what pressing it does.
+#[derive(Resource)]
+pub struct MenuFocus {
+    pub buttons: Vec<(&'static str, MenuAction)>,
+    pub index: usize,
+}
+
+impl MenuFocus {
+    pub fn new(buttons: Vec<(&'static str, MenuAction)>) -> Self {
+        Self { buttons, index: 0 }
+    }
+}
+
+pub fn play(commands: &mut Commands) {
+    commands.insert_resource(NextState(GameState::Playing));
+}
+
+pub fn to_menu(commands: &mut Commands) {
+    commands.insert_resource(NextState(GameState::Menu));
+}
+
+pub fn to_credits(commands: &mut Commands) {
+    commands.insert_resource(NextState(GameState::Credits));
+}
+
+pub(crate) fn navigate_menu(
+    mut commands: Commands,
+    controls: Controls,
+    focus: Option<ResMut<MenuFocus>>,
+    mut elements: Elements,
+) {
+    let mut focus = match focus {
+        Some(focus) if !focus.buttons.is_empty() => focus,
+        _ => return,
+    };
+
+    let count = focus.buttons.len();
+    if controls.anyone().just_pressed(Action::MenuNext) {
+        focus.index = (focus.index + 1) % count;
+    }
+    if controls.anyone().just_pressed(Action::MenuPrevious) {
+        focus.index = (focus.index + count - 1) % count;
+    }
+    if controls.anyone().just_pressed(Action::MenuConfirm) {
+        (focus.buttons[focus.index].1)(&mut commands);
+    }
+
+    if focus.is_changed() {
+        elements.select(".focused").remove_class("focused");
+        elements
+            .select(&format!(".{}", focus.buttons[focus.index].0))
+            .add_class("focused");
+    }
+}
+
+pub(crate) fn clear_menu_focus(mut commands: Commands) {
+    commands.remove_resource::<MenuFocus>();
+}
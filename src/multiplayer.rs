@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use iyes_loopless::state::NextState;
+use serde::{Deserialize, Serialize};
+
+use crate::{game_state::GameState, player::Player};
+
+pub const MAX_PLAYERS: usize = 4;
+/// How far apart the players start, side by side across their starting velocity.
+pub const PLAYER_SPACING: f32 = 80.;
+pub const PLAYER_COLORS: [Color; MAX_PLAYERS] = [
+    Color::WHITE,
+    Color::rgb(0.5, 0.9, 1.),
+    Color::rgb(1., 0.6, 0.8),
+    Color::rgb(0.7, 1., 0.5),
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Rules {
+    /// Everyone's points go towards one score, and any crash ends the run.
+    #[default]
+    Coop,
+    /// A crash knocks out only the player who crashed, and the last one flying
+    /// ends the run.
+    Competitive,
+}
+
+/// How many people are playing on this machine, and whether they're playing
+/// together or against each other.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Multiplayer {
+    pub players: usize,
+    pub rules: Rules,
+}
+
+impl Default for Multiplayer {
+    fn default() -> Self {
+        Self {
+            players: 1,
+            rules: Rules::Coop,
+        }
+    }
+}
+
+impl Multiplayer {
+    pub fn name(&self) -> String {
+        match (self.players, self.rules) {
+            (1, _) => "1 Player".to_string(),
+            (players, Rules::Coop) => format!("{players}P Co-op"),
+            (players, Rules::Competitive) => format!("{players}P Versus"),
+        }
+    }
+
+    /// One player, then co-op and versus for each larger group in turn.
+    pub fn next(&self) -> Self {
+        match (self.players, self.rules) {
+            (1, _) => Self {
+                players: 2,
+                rules: Rules::Coop,
+            },
+            (players, Rules::Coop) => Self {
+                players,
+                rules: Rules::Competitive,
+            },
+            (players, Rules::Competitive) if players < MAX_PLAYERS => Self {
+                players: players + 1,
+                rules: Rules::Coop,
+            },
+            _ => Self::default(),
+        }
+    }
+
+    pub fn is_competitive(&self) -> bool {
+        self.players > 1 && self.rules == Rules::Competitive
+    }
+
+    /// Whether the run is over with `remaining` players still flying.
+    pub fn is_over(&self, remaining: usize) -> bool {
+        remaining == 0 || (self.is_competitive() && remaining < 2)
+    }
+
+    /// Where each player starts, spread out side by side from `position`.
+    pub fn start_positions(&self, position: Vec2, velocity: Vec2) -> Vec<Vec2> {
+        let across = velocity.perp().normalize_or_zero();
+        let middle = (self.players - 1) as f32 / 2.;
+        (0..self.players)
+            .map(|index| position + across * PLAYER_SPACING * (index as f32 - middle))
+            .collect()
+    }
+}
+
+pub fn player_name(index: usize) -> String {
+    format!("P{}", index + 1)
+}
+
+pub fn player_color(index: usize) -> Color {
+    PLAYER_COLORS[index % MAX_PLAYERS]
+}
+
+/// Takes a crashed player out of the run: only them in a competitive game,
+/// or the whole run otherwise.
+pub fn knock_out(commands: &mut Commands, multiplayer: &Multiplayer, player: Entity) {
+    if multiplayer.is_competitive() {
+        commands.entity(player).despawn_recursive();
+    } else {
+        commands.insert_resource(NextState(GameState::GameOver));
+    }
+}
+
+pub fn cycle_multiplayer(world: &mut World) {
+    let mut multiplayer = world.resource_mut::<Multiplayer>();
+    *multiplayer = multiplayer.next();
+}
+
+pub(crate) fn check_players_left(
+    mut commands: Commands,
+    players: Query<(), With<Player>>,
+    multiplayer: Res<Multiplayer>,
+) {
+    if multiplayer.is_over(players.iter().count()) {
+        commands.insert_resource(NextState(GameState::GameOver));
+    }
+}
@@ -1,55 +1,244 @@
+use std::fmt;
+
 use crate::{
-    assets::GameAssets,
-    audio::ForegroundAudio,
-    level::{GoalStatus, LevelEvent},
+    effects::{ActiveEffects, EffectKind, EffectRegistry},
+    gravity::GravitationTransform,
+    gravity_spawner::WellAction,
+    inventory::Inventory,
+    level::{GoalOwner, GoalStatus, LevelEvent},
     player,
+    replay::{PhysicsTick, ReplayRecording},
+    score::{Score, ScoreEvent, ScoreKind, CHAIN_POINTS, DELIVERY_POINTS, NO_WELLS_POINTS},
+    spatial_index::SpatialGrid,
+    sweep::sweep_circle,
+    thrust::Fuel,
 };
 use bevy::prelude::*;
-use bevy_kira_audio::{AudioChannel, AudioControl};
+use serde::{
+    de::{self, EnumAccess, VariantAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 
 #[derive(Component)]
 pub struct Pickup(pub f32, pub PickupType);
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+/// What a pickup does once collected. Goals and fuel take effect straight
+/// away, and everything else is held in the inventory until it's used.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum PickupType {
     Goal,
-    PlanetKiller,
-    Teleport,
+    /// Refills the thrust tank straight away instead of being held.
+    Fuel,
+    Effect(EffectKind),
+}
+
+impl PickupType {
+    pub fn name(&self) -> &str {
+        match self {
+            PickupType::Goal => "Goal",
+            PickupType::Fuel => "Fuel",
+            PickupType::Effect(kind) => kind.name(),
+        }
+    }
+
+    /// The pickup with this name. Anything that isn't a goal or fuel is taken
+    /// to be an effect, which `EffectRegistry::parse` can check is registered.
+    pub fn named(name: &str) -> Self {
+        match name {
+            "Goal" => PickupType::Goal,
+            "Fuel" => PickupType::Fuel,
+            name => PickupType::Effect(EffectKind::new(name)),
+        }
+    }
+}
+
+// Goals and fuel are written as bare names, and effects as `Effect("Name")`.
+// Bare effect names, like level files use, are read as well.
+impl Serialize for PickupType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            PickupType::Goal => serializer.serialize_unit_variant("PickupType", 0, "Goal"),
+            PickupType::Fuel => serializer.serialize_unit_variant("PickupType", 1, "Fuel"),
+            PickupType::Effect(kind) => {
+                serializer.serialize_newtype_variant("PickupType", 2, "Effect", kind.name())
+            }
+        }
+    }
 }
 
-#[derive(Resource)]
-pub struct ActivePickup(pub Option<PickupType>);
+impl<'de> Deserialize<'de> for PickupType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// A variant name, which can be any effect's as well as the ones below.
+        struct VariantName(String);
+
+        impl<'de> Deserialize<'de> for VariantName {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_identifier(VariantNameVisitor)
+            }
+        }
+
+        struct VariantNameVisitor;
+
+        impl Visitor<'_> for VariantNameVisitor {
+            type Value = VariantName;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("the name of a pickup")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<VariantName, E> {
+                Ok(VariantName(name.to_owned()))
+            }
+        }
+
+        struct PickupVisitor;
+
+        impl<'de> Visitor<'de> for PickupVisitor {
+            type Value = PickupType;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a pickup")
+            }
 
-pub const PICKUPS: [PickupType; 2] = [PickupType::Teleport, PickupType::PlanetKiller];
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<PickupType, A::Error> {
+                let (VariantName(name), variant) = data.variant()?;
+                if name == "Effect" {
+                    let name = variant.newtype_variant::<String>()?;
+                    return Ok(PickupType::Effect(EffectKind::new(&name)));
+                }
+                variant.unit_variant()?;
+                Ok(PickupType::named(&name))
+            }
+        }
+
+        deserializer.deserialize_enum("PickupType", &["Goal", "Fuel", "Effect"], PickupVisitor)
+    }
+}
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn check_pickup(
     mut commands: Commands,
-    players: Query<&Transform, With<player::Player>>,
-    goals: Query<(Entity, &Transform, &Pickup)>,
+    mut players: Query<(
+        &player::Player,
+        &Transform,
+        &GravitationTransform,
+        &ActiveEffects,
+        &mut Inventory,
+        &mut Fuel,
+    )>,
+    goals: Query<(Entity, &Transform, &Pickup, Option<&GoalOwner>)>,
     mut goal_status: ResMut<GoalStatus>,
     mut events: EventWriter<LevelEvent>,
-    mut active_pickup: ResMut<ActivePickup>,
-    audio: Res<AudioChannel<ForegroundAudio>>,
-    assets: Res<GameAssets>,
+    score: Res<Score>,
+    tick: Res<PhysicsTick>,
+    recording: Res<ReplayRecording>,
+    mut score_events: EventWriter<ScoreEvent>,
+    registry: Res<EffectRegistry>,
 ) {
-    if matches!(active_pickup.0, Some(PickupType::Teleport)) {
-        return;
+    // Pickups are filed with room for the longest reach any player has.
+    let longest_reach = players
+        .iter()
+        .map(|(.., effects, _, _)| effects.collection_reach(&registry))
+        .fold(0., f32::max);
+    let mut grid = SpatialGrid::default();
+    for (entity, transform, pickup, owner) in goals.iter() {
+        let center = transform.translation.truncate();
+        grid.insert_swept(
+            (entity, center, pickup, owner.map(|owner| owner.0)),
+            center,
+            center,
+            pickup.0 + longest_reach,
+        );
     }
+    let mut collected = vec![];
+
+    for (player, transform, gravitation_transform, effects, mut inventory, mut fuel) in
+        players.iter_mut()
+    {
+        let reach = effects.collection_reach(&registry);
+        let no_wells =
+            !recording.0.placements.iter().any(|placement| {
+                placement.player == player.0 && placement.action == WellAction::Place
+            });
+        let player_score = score.player(player.0);
+
+        let (start, end) = gravitation_transform.last_step(transform);
+        let mut contacts = grid
+            .query_swept(start, end)
+            .filter(|(entity, .., owner)| {
+                !collected.contains(entity) && !matches!(owner, Some(owner) if *owner != player.0)
+            })
+            .filter_map(|(entity, center, pickup, _)| {
+                sweep_circle(start, end, *center, *center, pickup.0 + reach)
+                    .map(|contact| (*entity, *pickup, contact))
+            })
+            .collect::<Vec<_>>();
+        contacts.sort_by(|a, b| a.2.time.total_cmp(&b.2.time));
+        // Pickups that would go in the inventory are left alone once it's full.
+        let mut room = inventory.free_slots();
+        contacts.retain(|(_, pickup, _)| match pickup.1 {
+            PickupType::Goal | PickupType::Fuel => true,
+            PickupType::Effect(_) if room > 0 => {
+                room -= 1;
+                true
+            }
+            PickupType::Effect(_) => false,
+        });
 
-    for player in players.iter() {
-        for (entity, transform, pickup) in goals.iter() {
-            if player.translation.distance(transform.translation) <= pickup.0 {
-                commands.entity(entity).despawn_recursive();
-                if pickup.1 == PickupType::Goal {
-                    let goal_type = goal_status.current;
-                    goal_status.completed.push(goal_type);
-                    audio.play(assets.collected_audio.clone());
-                } else {
-                    active_pickup.0 = Some(pickup.1);
-                    audio.play(assets.pickup_audio.clone());
+        for (chain, (entity, pickup, _)) in (player_score.chain_at(tick.0)..).zip(contacts) {
+            commands.entity(entity).despawn_recursive();
+            collected.push(entity);
+            let award = |kind, points| ScoreEvent::new(player.0, kind, points);
+            match &pickup.1 {
+                PickupType::Goal => {
+                    if let Some(goal_type) = goal_status.current.get(player.0).copied() {
+                        goal_status.completed.push(goal_type);
+                    }
+                    score_events.send(award(ScoreKind::Delivery, DELIVERY_POINTS));
+                    let bonus = player_score.fast_delivery_bonus(tick.0);
+                    if bonus > 0 {
+                        score_events.send(award(ScoreKind::FastDelivery, bonus));
+                    }
+                    if no_wells {
+                        score_events.send(award(ScoreKind::NoWells, NO_WELLS_POINTS));
+                    }
+                }
+                PickupType::Fuel => fuel.refill(),
+                PickupType::Effect(kind) => {
+                    inventory.store(kind.clone());
                 }
-                events.send(LevelEvent::PickupCollected(pickup.1))
             }
+            score_events.send(award(
+                ScoreKind::PickupChain(chain),
+                CHAIN_POINTS * (chain - 1),
+            ));
+            events.send(LevelEvent::PickupCollected(pickup.1.clone(), player.0))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pickups_are_written_by_name() {
+        let pickups = vec![
+            PickupType::Goal,
+            PickupType::Effect(EffectKind::new("Shield")),
+            PickupType::Fuel,
+        ];
+
+        let written = ron::to_string(&pickups).unwrap();
+
+        assert_eq!(written, r#"[Goal,Effect("Shield"),Fuel]"#);
+        assert_eq!(
+            ron::from_str::<Vec<PickupType>>(&written),
+            Ok(pickups.clone())
+        );
+        assert_eq!(
+            ron::from_str::<Vec<PickupType>>("[Goal, Shield, Fuel]"),
+            Ok(pickups)
+        );
+    }
+}
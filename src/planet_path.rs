@@ -0,0 +1,91 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathCenter {
+    Point(Vec2),
+    Body(Entity),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathShape {
+    Orbit {
+        center: PathCenter,
+        radius: f32,
+        period: f32,
+        phase: f32,
+    },
+    /// Moves from `from` to `to` and back again once per period.
+    Patrol { from: Vec2, to: Vec2, period: f32 },
+}
+
+/// A body moved along a fixed path instead of by gravity. Positions only depend
+/// on the physics time, so the trajectory preview can look ahead exactly.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct PlanetPath {
+    pub shape: PathShape,
+    /// The physics time at which the body is at the start of its path.
+    pub started_at: f32,
+}
+
+impl PlanetPath {
+    /// An orbit that passes through `position` at `started_at`.
+    pub fn orbit_through(
+        center: PathCenter,
+        center_position: Vec2,
+        position: Vec2,
+        period: f32,
+        started_at: f32,
+    ) -> Self {
+        let offset = position - center_position;
+        Self {
+            shape: PathShape::Orbit {
+                center,
+                radius: offset.length(),
+                period,
+                phase: offset.y.atan2(offset.x),
+            },
+            started_at,
+        }
+    }
+
+    pub fn patrol(from: Vec2, to: Vec2, period: f32, started_at: f32) -> Self {
+        Self {
+            shape: PathShape::Patrol { from, to, period },
+            started_at,
+        }
+    }
+
+    pub fn center(&self) -> Option<PathCenter> {
+        match self.shape {
+            PathShape::Orbit { center, .. } => Some(center),
+            PathShape::Patrol { .. } => None,
+        }
+    }
+
+    /// The position at `time`, given where the orbit's center is at that time.
+    pub fn position_at(&self, time: f32, center_position: Vec2) -> Vec2 {
+        let elapsed = (time - self.started_at).max(0.);
+        match self.shape {
+            PathShape::Orbit {
+                radius,
+                period,
+                phase,
+                ..
+            } => {
+                let angle = phase + TAU * elapsed / period;
+                center_position + Vec2::new(angle.cos(), angle.sin()) * radius
+            }
+            PathShape::Patrol { from, to, period } => {
+                let progress = (elapsed / period).fract() * 2.;
+                let progress = if progress > 1. {
+                    2. - progress
+                } else {
+                    progress
+                };
+                from.lerp(to, progress)
+            }
+        }
+    }
+}
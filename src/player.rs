@@ -2,57 +2,46 @@ use bevy::prelude::*;
 
 use crate::{
     assets::GameAssets,
-    pickup::{ActivePickup, PickupType},
+    effects::{ActiveEffects, EffectRegistry},
+    multiplayer::player_color,
 };
-#[derive(Component)]
-pub struct Player;
+
+/// A player ship, numbered from zero in the order the players joined.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Player(pub usize);
 
 pub fn set_player_image(
     mut commands: Commands,
-    players: Query<Entity, With<Player>>,
-    active_pickup: Res<ActivePickup>,
+    players: Query<(Entity, &ActiveEffects), (With<Player>, Changed<ActiveEffects>)>,
     assets: Res<GameAssets>,
+    registry: Res<EffectRegistry>,
 ) {
-    if !active_pickup.is_changed() {
-        return;
-    }
-
-    let image = match active_pickup.0 {
-        Some(PickupType::PlanetKiller) => assets.player_planet_killer.clone(),
-        Some(PickupType::Teleport) => assets.player_teleport.clone(),
-        _ => assets.player.clone(),
-    };
-
-    for player in players.iter() {
-        commands.entity(player).insert(image.clone());
+    for (player, effects) in players.iter() {
+        let image = match effects.player_image(&registry) {
+            Some(image) => image(&assets),
+            None => assets.player.clone(),
+        };
+        commands.entity(player).insert(image);
     }
 }
 
-pub const PLANET_KILLER_FLASH_DURATION: f32 = 0.3;
+pub const EFFECT_FLASH_DURATION: f32 = 0.3;
+pub const INVERTER_TINT: Color = Color::rgb(0.6, 1., 0.8);
 
 pub fn player_has_pickup_modifiers(
-    mut players: Query<&mut Sprite, With<Player>>,
-    active_pickup: Res<ActivePickup>,
+    mut players: Query<(&mut Sprite, &Player, &ActiveEffects)>,
+    registry: Res<EffectRegistry>,
     time: Res<Time>,
 ) {
-    match active_pickup.0 {
-        Some(PickupType::PlanetKiller) => {
-            let division = time.elapsed_seconds() / PLANET_KILLER_FLASH_DURATION;
-            let division = division - division.floor();
-            let is_on = division > 0.5;
+    let division = time.elapsed_seconds() / EFFECT_FLASH_DURATION;
+    let division = division - division.floor();
+    let is_on = division > 0.5;
 
-            for mut player in players.iter_mut() {
-                player.color = if is_on {
-                    Color::rgb(0.8, 0.8, 0.8)
-                } else {
-                    Color::WHITE
-                };
-            }
-        }
-        _ => {
-            for mut player in players.iter_mut() {
-                player.color = Color::WHITE;
-            }
-        }
+    for (mut sprite, player, effects) in players.iter_mut() {
+        sprite.color = match effects.player_tint(&registry) {
+            _ if is_on && effects.flashes(&registry) => Color::rgb(0.8, 0.8, 0.8),
+            Some(tint) => tint,
+            None => player_color(player.0),
+        };
     }
 }
@@ -0,0 +1,309 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    effects::EffectRegistry,
+    gameplay_settings::GameplaySettings,
+    gravity::FIXED_TIME_DELTA,
+    gravity_spawner::{PendingPlacements, WellAction},
+    gravity_world::Integration,
+    inventory::PendingActivations,
+    multiplayer::Multiplayer,
+    pickup::PickupType,
+    player::Player,
+    thrust::ThrustInput,
+};
+
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhysicsTick(pub u64);
+
+impl PhysicsTick {
+    /// The simulated time, in seconds, once this tick's physics has run.
+    pub fn time(&self) -> f32 {
+        self.0 as f32 * FIXED_TIME_DELTA
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Placement {
+    pub tick: u64,
+    pub action: WellAction,
+    pub position: Vec2,
+    /// The effect that changed the placement, if any.
+    pub pickup: Option<PickupType>,
+    pub seed: u64,
+    /// Which player made the placement.
+    pub player: usize,
+}
+
+/// Every placement made during a run, along with each change of thrust input
+/// and every pickup put to use, written out as RON. Anything left out of a
+/// replay file is taken to be empty, and the settings, multiplayer rules and
+/// integration to be whatever the game is set to.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Replay {
+    pub seed: u64,
+    pub settings: Option<GameplaySettings>,
+    pub multiplayer: Option<Multiplayer>,
+    pub integration: Option<Integration>,
+    pub placements: Vec<Placement>,
+    /// Each player's thrust input from a tick onwards, recorded whenever it changes.
+    pub thrusts: Vec<(u64, usize, Vec2)>,
+    /// The tick, player and inventory slot of every pickup put to use.
+    pub activations: Vec<(u64, usize, usize)>,
+    pub end_tick: Option<u64>,
+}
+
+impl Replay {
+    pub fn new(
+        seed: u64,
+        settings: GameplaySettings,
+        multiplayer: Multiplayer,
+        integration: Integration,
+    ) -> Self {
+        Self {
+            seed,
+            settings: Some(settings),
+            multiplayer: Some(multiplayer),
+            integration: Some(integration),
+            ..default()
+        }
+    }
+
+    pub fn to_ron(&self) -> Result<String, String> {
+        ron::ser::to_string_pretty(self, PrettyConfig::default()).map_err(|e| e.to_string())
+    }
+
+    /// Reads a replay back, checking that every pickup in it is one the game knows about.
+    pub fn from_ron(source: &str, registry: &EffectRegistry) -> Result<Self, String> {
+        let mut replay: Replay = ron::from_str(source).map_err(|e| e.to_string())?;
+        for pickup in replay
+            .placements
+            .iter_mut()
+            .filter_map(|p| p.pickup.as_mut())
+        {
+            let known = registry
+                .parse(pickup.name())
+                .ok_or_else(|| format!("unknown pickup {}", pickup.name()))?;
+            *pickup = known;
+        }
+        Ok(replay)
+    }
+}
+
+#[derive(Resource, Default)]
+pub enum ReplayMode {
+    #[default]
+    Live,
+    Replaying(Replay),
+}
+
+#[derive(Resource, Default)]
+pub struct ReplayRecording(pub Replay);
+
+/// Where the recording of each finished run is written, if anywhere.
+#[derive(Resource, Default)]
+pub struct ReplayOutput(pub Option<PathBuf>);
+
+impl ReplayMode {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &str, registry: &EffectRegistry) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Ok(Self::Replaying(Replay::from_ron(&source, registry)?))
+    }
+}
+
+pub(crate) fn advance_tick(mut tick: ResMut<PhysicsTick>) {
+    tick.0 += 1;
+}
+
+pub(crate) fn feed_replay(
+    mode: Res<ReplayMode>,
+    tick: Res<PhysicsTick>,
+    mut pending: ResMut<PendingPlacements>,
+    mut activations: ResMut<PendingActivations>,
+    mut players: Query<(&Player, &mut ThrustInput)>,
+) {
+    if let ReplayMode::Replaying(replay) = mode.as_ref() {
+        for placement in replay.placements.iter().filter(|p| p.tick == tick.0) {
+            pending
+                .0
+                .push((placement.player, placement.action, placement.position));
+        }
+        for (_, player, slot) in replay.activations.iter().filter(|(t, ..)| *t == tick.0) {
+            activations.0.push((*player, *slot));
+        }
+        for (_, index, input) in replay.thrusts.iter().filter(|(t, ..)| *t == tick.0) {
+            for (_, mut thrust) in players.iter_mut().filter(|(player, _)| player.0 == *index) {
+                thrust.0 = *input;
+            }
+        }
+    }
+}
+
+pub(crate) fn finish_recording(
+    tick: Res<PhysicsTick>,
+    mode: Res<ReplayMode>,
+    output: Res<ReplayOutput>,
+    mut recording: ResMut<ReplayRecording>,
+) {
+    recording.0.end_tick = Some(tick.0);
+
+    if let ReplayMode::Replaying(replay) = mode.as_ref() {
+        if replay.end_tick != recording.0.end_tick {
+            warn!(
+                "Replay diverged: recorded run ended at tick {:?}, replay ended at tick {}",
+                replay.end_tick, tick.0
+            );
+        } else if replay.placements != recording.0.placements {
+            warn!("Replay diverged: placements were applied with a different pickup state");
+        } else if replay.thrusts != recording.0.thrusts {
+            warn!("Replay diverged: thrust was applied on different ticks");
+        } else if replay.activations != recording.0.activations {
+            warn!("Replay diverged: pickups were used on different ticks");
+        } else {
+            info!("Replay matched the recorded run, ending at tick {}", tick.0);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = &output.0 {
+        let written = recording
+            .0
+            .to_ron()
+            .and_then(|ron| std::fs::write(path, ron).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            error!("Couldn't write replay to {path:?}: {e}");
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    let _ = output;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        effects::{teleport::TELEPORT, EffectKind, EffectRules},
+        gameplay_settings::Difficulty,
+        gravity_world::Integrator,
+        multiplayer::Rules,
+    };
+
+    fn registry() -> EffectRegistry {
+        let mut registry = EffectRegistry::default();
+        registry.register(TELEPORT, EffectRules::default());
+        registry
+    }
+
+    fn recorded_run() -> Replay {
+        Replay {
+            seed: 1234,
+            settings: Some(GameplaySettings::preset(Difficulty::Hard)),
+            multiplayer: Some(Multiplayer {
+                players: 2,
+                rules: Rules::Competitive,
+            }),
+            integration: Some(Integration {
+                integrator: Integrator::Rk4,
+                substeps: 3,
+            }),
+            placements: vec![
+                Placement {
+                    tick: 12,
+                    action: WellAction::Place,
+                    position: Vec2::new(-120.5, 33.25),
+                    pickup: None,
+                    seed: 1234,
+                    player: 0,
+                },
+                Placement {
+                    tick: 40,
+                    action: WellAction::Place,
+                    position: Vec2::new(0.1, -0.3),
+                    pickup: Some(PickupType::Effect(EffectKind::new(TELEPORT))),
+                    seed: 1234,
+                    player: 1,
+                },
+                Placement {
+                    tick: 41,
+                    action: WellAction::Remove,
+                    position: Vec2::new(1e-3, 250.),
+                    pickup: None,
+                    seed: 1234,
+                    player: 1,
+                },
+            ],
+            thrusts: vec![(5, 0, Vec2::new(0., 1.)), (9, 1, Vec2::new(-0.6, 0.8))],
+            activations: vec![(38, 1, 2)],
+            end_tick: Some(300),
+        }
+    }
+
+    #[test]
+    fn written_replay_reads_back_the_same() {
+        let replay = recorded_run();
+        let written = replay.to_ron().unwrap();
+
+        assert_eq!(Replay::from_ron(&written, &registry()), Ok(replay));
+    }
+
+    #[test]
+    fn empty_replay_reads_back_the_same() {
+        let replay = Replay::new(
+            7,
+            GameplaySettings::default(),
+            Multiplayer::default(),
+            Integration::default(),
+        );
+        let written = replay.to_ron().unwrap();
+
+        assert_eq!(Replay::from_ron(&written, &registry()), Ok(replay));
+    }
+
+    #[test]
+    fn missing_parts_are_left_empty() {
+        let replay = Replay::from_ron(
+            "(
+                seed: 99,
+                placements: [
+                    (tick: 3, action: Place, position: (10., -20.), pickup: None, seed: 99, player: 0),
+                ],
+                end_tick: Some(50),
+            )",
+            &registry(),
+        )
+        .unwrap();
+
+        assert_eq!(replay.seed, 99);
+        assert_eq!(replay.settings, None);
+        assert_eq!(replay.multiplayer, None);
+        assert_eq!(replay.integration, None);
+        assert_eq!(replay.placements.len(), 1);
+        assert!(replay.thrusts.is_empty());
+        assert!(replay.activations.is_empty());
+        assert_eq!(replay.end_tick, Some(50));
+    }
+
+    #[test]
+    fn unknown_pickups_and_malformed_replays_are_rejected() {
+        let placement = |pickup: &str| {
+            format!(
+                "(placements: [(tick: 3, action: Place, position: (10., -20.), \
+                 pickup: {pickup}, seed: 1, player: 0)])"
+            )
+        };
+        assert!(Replay::from_ron(&placement("Some(Teleport)"), &registry()).is_ok());
+        assert!(Replay::from_ron(&placement("Some(NotAPickup)"), &registry()).is_err());
+        assert!(
+            Replay::from_ron(&placement(r#"Some(Effect("NotAPickup"))"#), &registry()).is_err()
+        );
+        assert!(Replay::from_ron("(wobble: 3)", &registry()).is_err());
+        assert!(Replay::from_ron("(thrusts: [(1, 0)])", &registry()).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+
+use crate::{
+    gravity::FIXED_TIME_DELTA,
+    multiplayer::{player_name, Multiplayer},
+    replay::PhysicsTick,
+};
+
+pub const DELIVERY_POINTS: u32 = 100;
+/// Deliveries made within this many seconds of the goal appearing earn a bonus.
+pub const FAST_DELIVERY_TIME: f32 = 20.;
+pub const FAST_DELIVERY_POINTS_PER_SECOND: f32 = 10.;
+pub const NO_WELLS_POINTS: u32 = 150;
+/// Collecting anything within this many seconds of the last collection continues the chain.
+pub const CHAIN_WINDOW: f32 = 8.;
+pub const CHAIN_POINTS: u32 = 25;
+pub const NEAR_MISS_POINTS: u32 = 50;
+/// How close to a body's crash radius a flyby has to come to count as a near miss.
+pub const NEAR_MISS_MARGIN: f32 = 25.;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScoreKind {
+    Delivery,
+    FastDelivery,
+    NoWells,
+    /// The number of collections in the chain so far.
+    PickupChain(u32),
+    NearMiss,
+}
+
+impl ScoreKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ScoreKind::Delivery => "Deliveries",
+            ScoreKind::FastDelivery => "Fast deliveries",
+            ScoreKind::NoWells => "No wells",
+            ScoreKind::PickupChain(_) => "Pickup chains",
+            ScoreKind::NearMiss => "Near misses",
+        }
+    }
+
+    pub fn describe(&self, points: u32) -> String {
+        match self {
+            ScoreKind::Delivery => format!("Delivered +{points}"),
+            ScoreKind::FastDelivery => format!("Fast delivery +{points}"),
+            ScoreKind::NoWells => format!("No wells +{points}"),
+            ScoreKind::PickupChain(chain) => format!("Chain x{chain} +{points}"),
+            ScoreKind::NearMiss => format!("Near miss +{points}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScoreEvent {
+    pub player: usize,
+    pub kind: ScoreKind,
+    pub points: u32,
+}
+
+impl ScoreEvent {
+    pub fn new(player: usize, kind: ScoreKind, points: u32) -> Self {
+        Self {
+            player,
+            kind,
+            points,
+        }
+    }
+}
+
+/// A single player's share of the score, along with what their bonuses depend on.
+#[derive(Default, Debug, Clone)]
+pub struct PlayerScore {
+    pub points: u32,
+    pub chain: u32,
+    pub last_collected_at: Option<u64>,
+    /// The tick the player's current goal was handed out on.
+    pub goal_started_at: u64,
+}
+
+impl PlayerScore {
+    /// How long the current chain would be after collecting something on `tick`.
+    pub fn chain_at(&self, tick: u64) -> u32 {
+        match self.last_collected_at {
+            Some(last) if tick.saturating_sub(last) as f32 * FIXED_TIME_DELTA <= CHAIN_WINDOW => {
+                self.chain + 1
+            }
+            _ => 1,
+        }
+    }
+
+    /// The bonus for delivering the current goal on `tick`.
+    pub fn fast_delivery_bonus(&self, tick: u64) -> u32 {
+        let elapsed = tick.saturating_sub(self.goal_started_at) as f32 * FIXED_TIME_DELTA;
+        ((FAST_DELIVERY_TIME - elapsed).max(0.) * FAST_DELIVERY_POINTS_PER_SECOND) as u32
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone)]
+pub struct Score {
+    /// Everyone's points together.
+    pub points: u32,
+    /// The latest award, shown briefly in the in-game UI.
+    pub last_award: String,
+    /// Points per kind of award, in the order they were first earned.
+    pub breakdown: Vec<(&'static str, u32)>,
+    pub players: Vec<PlayerScore>,
+}
+
+impl Score {
+    pub fn new(players: usize) -> Self {
+        Self {
+            players: vec![PlayerScore::default(); players],
+            ..default()
+        }
+    }
+
+    pub fn player(&self, player: usize) -> PlayerScore {
+        self.players.get(player).cloned().unwrap_or_default()
+    }
+
+    /// Each player's index and points, best first.
+    pub fn standings(&self) -> Vec<(usize, u32)> {
+        let mut standings = self
+            .players
+            .iter()
+            .enumerate()
+            .map(|(index, player)| (index, player.points))
+            .collect::<Vec<_>>();
+        standings.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        standings
+    }
+}
+
+/// Bodies each player is currently flying close by. Leaving one's range
+/// without crashing counts as a near miss.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct NearMisses(pub Vec<(usize, Entity)>);
+
+pub(crate) fn reset_score(mut commands: Commands, multiplayer: Res<Multiplayer>) {
+    commands.insert_resource(Score::new(multiplayer.players));
+    commands.insert_resource(NearMisses::default());
+}
+
+pub(crate) fn tally_score(
+    mut events: EventReader<ScoreEvent>,
+    mut score: ResMut<Score>,
+    tick: Res<PhysicsTick>,
+    multiplayer: Res<Multiplayer>,
+) {
+    for event in events.iter() {
+        if score.players.len() <= event.player {
+            score.players.resize(event.player + 1, default());
+        }
+        if event.points > 0 {
+            score.points += event.points;
+            score.players[event.player].points += event.points;
+            let award = event.kind.describe(event.points);
+            score.last_award = if multiplayer.players > 1 {
+                format!("{} {award}", player_name(event.player))
+            } else {
+                award
+            };
+            let label = event.kind.label();
+            match score.breakdown.iter_mut().find(|(kind, _)| *kind == label) {
+                Some((_, points)) => *points += event.points,
+                None => score.breakdown.push((label, event.points)),
+            }
+        }
+        let player = &mut score.players[event.player];
+        match event.kind {
+            ScoreKind::Delivery => player.goal_started_at = tick.0,
+            ScoreKind::PickupChain(chain) => {
+                player.chain = chain;
+                player.last_collected_at = Some(tick.0);
+            }
+            _ => {}
+        }
+    }
+}
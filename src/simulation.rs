@@ -0,0 +1,327 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::{
+    AppLooplessFixedTimestepExt, AppLooplessStateExt, ConditionSet, NextState,
+};
+
+use crate::{
+    effects::{self, EffectsPlugin},
+    game_state::GameState,
+    gameplay_settings::GameplaySettings,
+    gravity::{self, CrashEvent, FIXED_TIME_MILIS},
+    gravity_spawner::{self, PendingPlacements, WellRules},
+    gravity_world::Integration,
+    inventory::{self, PendingActivations},
+    level::{self, GoalStatus, LevelEvent},
+    multiplayer::{self, Multiplayer},
+    pickup,
+    replay::{self, PhysicsTick, ReplayMode, ReplayOutput, ReplayRecording},
+    score::{self, NearMisses, Score, ScoreEvent},
+    thrust,
+    time_scale::{self, TimeScale},
+};
+
+/// Everything needed to move bodies and resolve crashes, pickups and boundary exits.
+/// It has no rendering, audio or asset dependencies, so it runs on `MinimalPlugins`.
+///
+/// Each fixed tick runs in three sub-stages: pickups being used and placements,
+/// gravity, then collisions and scoring. How often the ticks come round follows
+/// the `TimeScale`.
+/// Further sub-stages added after these see the results of the whole tick.
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LevelEvent>()
+            .add_event::<CrashEvent>()
+            .add_event::<ScoreEvent>()
+            .insert_resource(level::LevelBoundary {
+                min: Vec2::new(-500., -300.),
+                max: Vec2::new(500., 300.),
+            })
+            .insert_resource(GoalStatus::new(1))
+            .init_resource::<PendingPlacements>()
+            .init_resource::<PendingActivations>()
+            .init_resource::<WellRules>()
+            .init_resource::<Integration>()
+            .init_resource::<GameplaySettings>()
+            .init_resource::<PhysicsTick>()
+            .init_resource::<ReplayMode>()
+            .init_resource::<ReplayRecording>()
+            .init_resource::<ReplayOutput>()
+            .init_resource::<Score>()
+            .init_resource::<NearMisses>()
+            .init_resource::<Multiplayer>()
+            .init_resource::<TimeScale>()
+            .add_loopless_state(GameState::Loading)
+            .add_enter_system(GameState::Playing, score::reset_score)
+            .add_exit_system(GameState::Playing, replay::finish_recording)
+            .add_system_to_stage(CoreStage::PreUpdate, effects::scale_time)
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                time_scale::apply_time_scale.after(effects::scale_time),
+            )
+            .add_fixed_timestep(Duration::from_millis(FIXED_TIME_MILIS), "calculate_physics")
+            .add_fixed_timestep_child_stage("calculate_physics")
+            .add_fixed_timestep_child_stage("calculate_physics")
+            .add_plugin(EffectsPlugin)
+            .add_fixed_timestep_system_set(
+                "calculate_physics",
+                0,
+                physics_tick_set()
+                    .label("tick")
+                    .with_system(replay::advance_tick)
+                    .with_system(level::announce_level_start)
+                    .into(),
+            )
+            .add_fixed_timestep_system_set(
+                "calculate_physics",
+                0,
+                physics_tick_set()
+                    .label("replay_input")
+                    .after("tick")
+                    .with_system(replay::feed_replay)
+                    .into(),
+            )
+            .add_fixed_timestep_system_set(
+                "calculate_physics",
+                0,
+                physics_tick_set()
+                    .label("activations")
+                    .after("replay_input")
+                    .with_system(inventory::apply_activations)
+                    .into(),
+            )
+            .add_fixed_timestep_system_set(
+                "calculate_physics",
+                0,
+                physics_tick_set()
+                    .after("activations")
+                    .with_system(gravity_spawner::apply_placements)
+                    .with_system(effects::tick_effects)
+                    .with_system(gravity_spawner::expire_wells)
+                    .with_system(gravity::tick_delayed_activity)
+                    .with_system(thrust::apply_thrust)
+                    .into(),
+            )
+            .add_fixed_timestep_system_set(
+                "calculate_physics",
+                1,
+                physics_tick_set()
+                    .with_system(gravity::calculate_gravity)
+                    .with_system(gravity::adjust_rotation)
+                    .into(),
+            )
+            // A crash can send the player somewhere else, so it's settled before
+            // they collect anything along the way or leave the level.
+            .add_fixed_timestep_system_set(
+                "calculate_physics",
+                2,
+                physics_tick_set()
+                    .label("collisions")
+                    .label("crashes")
+                    .with_system(gravity::check_crash)
+                    .into(),
+            )
+            .add_fixed_timestep_system_set(
+                "calculate_physics",
+                2,
+                physics_tick_set()
+                    .label("collisions")
+                    .label("pickups")
+                    .after("crashes")
+                    .with_system(pickup::check_pickup)
+                    .into(),
+            )
+            .add_fixed_timestep_system_set(
+                "calculate_physics",
+                2,
+                physics_tick_set()
+                    .label("collisions")
+                    .after("pickups")
+                    .with_system(level::check_boundary)
+                    .into(),
+            )
+            .add_fixed_timestep_system_set(
+                "calculate_physics",
+                2,
+                physics_tick_set()
+                    .after("collisions")
+                    .with_system(score::tally_score)
+                    .with_system(multiplayer::check_players_left)
+                    .into(),
+            )
+            .add_system_set(
+                ConditionSet::new()
+                    .label("simulation")
+                    .run_in_state(GameState::Playing)
+                    .with_system(gravity::smooth_movement)
+                    .into(),
+            );
+    }
+}
+
+/// Fixed ticks stop as soon as a state change is queued, so a frame that runs
+/// several ticks can't carry on simulating after a crash or boundary exit.
+pub(crate) fn physics_tick_set() -> ConditionSet {
+    ConditionSet::new()
+        .run_in_state(GameState::Playing)
+        .run_unless_resource_exists::<NextState<GameState>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        effects::{shield::SHIELD, teleport::TELEPORT, ActiveEffects, EffectKind, EffectRegistry},
+        gravity::{GravitationTransform, GravitationalBody},
+        gravity_spawner::WellAction,
+        inventory::Inventory,
+        pickup::{Pickup, PickupType},
+        player::Player,
+        thrust::{Fuel, ThrustInput},
+    };
+    use iyes_loopless::state::CurrentState;
+
+    /// A headless game that has just started playing, with the fixed
+    /// timestep paused so the tests decide when each tick runs.
+    fn playing() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(SimulationPlugin)
+            .insert_resource(NextState(GameState::Playing));
+        app.world.resource_mut::<TimeScale>().paused = true;
+        app.update();
+        app
+    }
+
+    /// Runs the fixed timestep `ticks` times, a frame at a time.
+    fn run_ticks(app: &mut App, ticks: u32) {
+        for _ in 0..ticks {
+            app.world.resource_mut::<TimeScale>().steps = 1;
+            app.update();
+        }
+    }
+
+    fn spawn_player(app: &mut App, position: Vec2, velocity: Vec2) -> Entity {
+        app.world
+            .spawn((
+                Transform::from_translation(position.extend(0.)),
+                GravitationTransform::velocity(velocity),
+                GravitationalBody(1., 5.),
+                Player(0),
+                ActiveEffects::default(),
+                Inventory::default(),
+                Fuel::default(),
+                ThrustInput::default(),
+            ))
+            .id()
+    }
+
+    fn state(app: &App) -> GameState {
+        app.world.resource::<CurrentState<GameState>>().0.clone()
+    }
+
+    #[test]
+    fn ticks_only_run_when_stepped() {
+        let mut app = playing();
+        let player = spawn_player(&mut app, Vec2::ZERO, Vec2::new(150., 0.));
+
+        app.update();
+        assert_eq!(*app.world.resource::<PhysicsTick>(), PhysicsTick(0));
+
+        run_ticks(&mut app, 3);
+        assert_eq!(*app.world.resource::<PhysicsTick>(), PhysicsTick(3));
+        let position = app
+            .world
+            .get::<GravitationTransform>(player)
+            .unwrap()
+            .simulated_position(app.world.get::<Transform>(player).unwrap());
+        assert!(position.distance(Vec2::new(30., 0.)) < 1e-3);
+    }
+
+    #[test]
+    fn flying_into_a_planet_ends_the_run() {
+        let mut app = playing();
+        spawn_player(&mut app, Vec2::ZERO, Vec2::new(200., 0.));
+        app.world.spawn((
+            Transform::from_xyz(100., 0., 0.),
+            GravitationTransform::Static,
+            GravitationalBody(1000., 30.),
+        ));
+
+        run_ticks(&mut app, 10);
+
+        assert_eq!(state(&app), GameState::GameOver);
+    }
+
+    #[test]
+    fn flying_through_a_pickup_collects_it() {
+        let mut app = playing();
+        let player = spawn_player(&mut app, Vec2::ZERO, Vec2::new(200., 0.));
+        let pickup = app
+            .world
+            .spawn((
+                Transform::from_xyz(60., 0., 0.),
+                Pickup(10., PickupType::Effect(EffectKind::new(SHIELD))),
+            ))
+            .id();
+
+        run_ticks(&mut app, 10);
+
+        assert_eq!(state(&app), GameState::Playing);
+        assert!(app.world.get_entity(pickup).is_none());
+        assert_eq!(
+            app.world.get::<Inventory>(player).unwrap().slots[0],
+            Some(EffectKind::new(SHIELD))
+        );
+    }
+
+    #[test]
+    fn a_new_level_is_announced_on_its_first_tick() {
+        let mut app = playing();
+        app.world.insert_resource(level::PendingLevelStart);
+
+        app.update();
+        assert!(app.world.contains_resource::<level::PendingLevelStart>());
+
+        run_ticks(&mut app, 1);
+        assert!(!app.world.contains_resource::<level::PendingLevelStart>());
+        let events = app.world.resource::<Events<LevelEvent>>();
+        assert!(events
+            .get_reader()
+            .iter(events)
+            .any(|event| matches!(event, LevelEvent::LevelStarted)));
+    }
+
+    #[test]
+    fn placements_record_the_effect_that_changed_them() {
+        let mut app = playing();
+        let player = spawn_player(&mut app, Vec2::ZERO, Vec2::new(150., 0.));
+        app.world
+            .resource_scope(|world, registry: Mut<EffectRegistry>| {
+                let mut effects = world.get_mut::<ActiveEffects>(player).unwrap();
+                effects.start(TELEPORT, &registry);
+            });
+        app.world.resource_mut::<PendingPlacements>().0.push((
+            0,
+            WellAction::Place,
+            Vec2::new(50., 80.),
+        ));
+
+        run_ticks(&mut app, 1);
+
+        let placements = &app.world.resource::<ReplayRecording>().0.placements;
+        assert_eq!(placements.len(), 1);
+        assert_eq!(
+            placements[0].pickup,
+            Some(PickupType::Effect(EffectKind::new(TELEPORT)))
+        );
+        assert!(!app
+            .world
+            .get::<ActiveEffects>(player)
+            .unwrap()
+            .has(TELEPORT));
+    }
+}
@@ -0,0 +1,290 @@
+use bevy::{prelude::*, utils::HashMap};
+
+/// Width of each cell in a `SpatialGrid`, roughly the size of a large planet plus its gap.
+pub const CELL_SIZE: f32 = 128.;
+
+/// How close a node's center of mass has to be, relative to its size, before
+/// it is opened up instead of being treated as a single body.
+pub const BARNES_HUT_THETA: f32 = 0.5;
+
+const LEAF_SIZE: usize = 4;
+const MAX_DEPTH: usize = 16;
+
+/// A uniform grid of axis aligned boxes, used to narrow collision and spacing
+/// checks down to the items that could possibly overlap.
+#[derive(Clone, Debug)]
+pub struct SpatialGrid<T> {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<usize>>,
+    items: Vec<T>,
+}
+
+impl<T> Default for SpatialGrid<T> {
+    fn default() -> Self {
+        Self::new(CELL_SIZE)
+    }
+}
+
+impl<T> SpatialGrid<T> {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+            items: vec![],
+        }
+    }
+
+    fn cell(&self, point: Vec2) -> IVec2 {
+        (point / self.cell_size).floor().as_ivec2()
+    }
+
+    fn cells_between(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = IVec2> {
+        let min = self.cell(min);
+        let max = self.cell(max);
+        (min.x..=max.x).flat_map(move |x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+    }
+
+    /// Adds an item covering the box from `min` to `max`.
+    pub fn insert(&mut self, item: T, min: Vec2, max: Vec2) {
+        let index = self.items.len();
+        self.items.push(item);
+        for cell in self.cells_between(min, max).collect::<Vec<_>>() {
+            self.cells.entry(cell).or_default().push(index);
+        }
+    }
+
+    /// Adds an item covering a circle that moves from `start` to `end`.
+    pub fn insert_swept(&mut self, item: T, start: Vec2, end: Vec2, radius: f32) {
+        self.insert(item, start.min(end) - radius, start.max(end) + radius);
+    }
+
+    /// Every item whose box might overlap the box from `min` to `max`, in insertion order.
+    pub fn query(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = &T> {
+        let mut indices = self
+            .cells_between(min, max)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        indices.sort_unstable();
+        indices.dedup();
+        indices.into_iter().map(|index| &self.items[index])
+    }
+
+    /// Every item that might be touched by a point moving from `start` to `end`.
+    pub fn query_swept(&self, start: Vec2, end: Vec2) -> impl Iterator<Item = &T> {
+        self.query(start.min(end), start.max(end))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    min: Vec2,
+    size: f32,
+    mass: f32,
+    center_of_mass: Vec2,
+    children: Option<[usize; 4]>,
+    bodies: Vec<usize>,
+}
+
+/// A Barnes–Hut quadtree over point masses. Distant groups of bodies are
+/// treated as one body at their center of mass, so pulling on every body costs
+/// `O(n log n)` instead of `O(n²)`.
+///
+/// Centers of mass only make sense for positive masses, so anything else
+/// should be summed directly.
+#[derive(Clone, Debug, Default)]
+pub struct QuadTree {
+    nodes: Vec<Node>,
+    positions: Vec<Vec2>,
+    masses: Vec<f32>,
+}
+
+impl QuadTree {
+    /// Builds a tree over the bodies at `positions`. Bodies are referred to by
+    /// their index, and only the ones in `members` are added.
+    pub fn build(positions: &[Vec2], masses: &[f32], members: Vec<usize>) -> Self {
+        let mut tree = Self {
+            nodes: vec![],
+            positions: positions.to_vec(),
+            masses: masses.to_vec(),
+        };
+        if members.is_empty() {
+            return tree;
+        }
+        let (min, max) = members.iter().fold(
+            (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+            |(min, max), index| (min.min(positions[*index]), max.max(positions[*index])),
+        );
+        let size = (max - min).max_element().max(1.);
+        tree.build_node(members, min, size, 0);
+        tree
+    }
+
+    fn build_node(&mut self, bodies: Vec<usize>, min: Vec2, size: f32, depth: usize) -> usize {
+        let mass = bodies.iter().map(|index| self.masses[*index]).sum::<f32>();
+        let center_of_mass = if mass > 0. {
+            bodies
+                .iter()
+                .map(|index| self.positions[*index] * self.masses[*index])
+                .sum::<Vec2>()
+                / mass
+        } else {
+            min + size / 2.
+        };
+
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            min,
+            size,
+            mass,
+            center_of_mass,
+            children: None,
+            bodies: vec![],
+        });
+
+        if bodies.len() <= LEAF_SIZE || depth >= MAX_DEPTH {
+            self.nodes[index].bodies = bodies;
+            return index;
+        }
+
+        let half = size / 2.;
+        let middle = min + half;
+        let mut quadrants: [Vec<usize>; 4] = default();
+        for body in bodies {
+            let position = self.positions[body];
+            let quadrant =
+                (position.x >= middle.x) as usize + 2 * (position.y >= middle.y) as usize;
+            quadrants[quadrant].push(body);
+        }
+        let mut children = [0; 4];
+        for (quadrant, bodies) in quadrants.into_iter().enumerate() {
+            let offset = Vec2::new((quadrant % 2) as f32, (quadrant / 2) as f32) * half;
+            children[quadrant] = self.build_node(bodies, min + offset, half, depth + 1);
+        }
+        self.nodes[index].children = Some(children);
+        index
+    }
+
+    /// Calls `pull` with the position and mass of every body, or group of
+    /// bodies, that acts on `position`. The body at index `exclude` is skipped.
+    pub fn visit(&self, position: Vec2, exclude: usize, mut pull: impl FnMut(Vec2, f32)) {
+        if !self.nodes.is_empty() {
+            self.visit_node(0, position, exclude, &mut pull);
+        }
+    }
+
+    fn visit_node(
+        &self,
+        index: usize,
+        position: Vec2,
+        exclude: usize,
+        pull: &mut impl FnMut(Vec2, f32),
+    ) {
+        let node = &self.nodes[index];
+        if node.mass <= 0. {
+            return;
+        }
+        match node.children {
+            None => {
+                for body in node.bodies.iter().filter(|body| **body != exclude) {
+                    pull(self.positions[*body], self.masses[*body]);
+                }
+            }
+            Some(children) => {
+                let contains =
+                    position.cmpge(node.min).all() && position.cmple(node.min + node.size).all();
+                let distance_squared = node.center_of_mass.distance_squared(position);
+                if !contains
+                    && node.size * node.size
+                        < BARNES_HUT_THETA * BARNES_HUT_THETA * distance_squared
+                {
+                    pull(node.center_of_mass, node.mass);
+                } else {
+                    for child in children {
+                        self.visit_node(child, position, exclude, pull);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swept_queries_find_items_along_the_path() {
+        let mut grid = SpatialGrid::new(10.);
+        grid.insert("start", Vec2::new(1., 1.), Vec2::new(2., 2.));
+        grid.insert("middle", Vec2::new(24., 14.), Vec2::new(26., 16.));
+        grid.insert("behind", Vec2::new(-30., -30.), Vec2::new(-25., -25.));
+        grid.insert("wide", Vec2::new(-100., 35.), Vec2::new(100., 38.));
+        grid.insert_swept("moving", Vec2::new(60., 0.), Vec2::new(60., 100.), 3.);
+
+        let found = grid
+            .query_swept(Vec2::new(45., 45.), Vec2::new(5., 5.))
+            .copied()
+            .collect::<Vec<_>>();
+
+        assert_eq!(found, vec!["start", "middle", "wide"]);
+    }
+
+    #[test]
+    fn swept_items_are_found_anywhere_along_their_path() {
+        let mut grid = SpatialGrid::new(10.);
+        grid.insert_swept(1, Vec2::new(-50., 0.), Vec2::new(50., 0.), 5.);
+
+        assert_eq!(
+            grid.query_swept(Vec2::new(0., 4.), Vec2::new(0., 4.))
+                .count(),
+            1
+        );
+        assert_eq!(
+            grid.query_swept(Vec2::new(0., 30.), Vec2::new(10., 40.))
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn barnes_hut_matches_the_direct_sum() {
+        // A small linear congruential generator keeps the layout the same every run.
+        let mut state = 12345_u32;
+        let mut next = || {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (state >> 8) as f32 / (1 << 24) as f32
+        };
+        let positions = (0..200)
+            .map(|_| Vec2::new(next(), next()) * 2000. - 1000.)
+            .collect::<Vec<_>>();
+        let masses = (0..200).map(|_| 100. + next() * 10000.).collect::<Vec<_>>();
+        let pull = |position: Vec2, other: Vec2, mass: f32| {
+            let offset = other - position;
+            mass * offset / offset.length().powi(3)
+        };
+        let tree = QuadTree::build(&positions, &masses, (0..positions.len()).collect());
+
+        for (index, position) in positions.iter().enumerate() {
+            let pulls = positions
+                .iter()
+                .zip(&masses)
+                .enumerate()
+                .filter(|(other, _)| *other != index)
+                .map(|(_, (other, mass))| pull(*position, *other, *mass))
+                .collect::<Vec<_>>();
+            let direct = pulls.iter().sum::<Vec2>();
+            // Opposing pulls can nearly cancel out, so the error is measured
+            // against the pulls themselves rather than what's left of them.
+            let scale = pulls.iter().map(|pull| pull.length()).sum::<f32>();
+            let mut approximate = Vec2::ZERO;
+            tree.visit(*position, index, |other, mass| {
+                approximate += pull(*position, other, mass)
+            });
+
+            let error = approximate.distance(direct) / scale;
+            assert!(error < 0.01, "body {index} is off by {error}");
+        }
+    }
+}
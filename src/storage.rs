@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path_for(key: &str) -> Option<std::path::PathBuf> {
+    directories::ProjectDirs::from("", "lee-orr", "galactic-errands")
+        .map(|dirs| dirs.data_dir().join(format!("{key}.ron")))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read(key: &str) -> Option<String> {
+    std::fs::read_to_string(path_for(key)?).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(key: &str, value: &str) -> Result<(), String> {
+    let path = path_for(key).ok_or("no data directory available")?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(path, value).map_err(|e| e.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read(key: &str) -> Option<String> {
+    local_storage()?.get_item(key).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(key: &str, value: &str) -> Result<(), String> {
+    local_storage()
+        .ok_or("local storage isn't available")?
+        .set_item(key, value)
+        .map_err(|e| format!("{e:?}"))
+}
+
+/// Reads a value saved with [`save`], falling back to the default if it is missing or unreadable.
+pub fn load<T: DeserializeOwned + Default>(key: &str) -> T {
+    read(key)
+        .and_then(|value| match ron::from_str(&value) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!("Couldn't read saved {key}: {e}");
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    let result = ron::to_string(value)
+        .map_err(|e| e.to_string())
+        .and_then(|value| write(key, &value));
+    if let Err(e) = result {
+        error!("Couldn't save {key}: {e}");
+    }
+}
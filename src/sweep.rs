@@ -0,0 +1,210 @@
+use bevy::prelude::*;
+
+/// Where and when a point moving over one fixed step first touches a circle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    /// The point's position at the moment of contact, on the circle's edge.
+    pub point: Vec2,
+    /// How far through the step contact happens, from 0 to 1.
+    pub time: f32,
+    /// Points out of the circle, towards the contact point.
+    pub normal: Vec2,
+}
+
+/// Sweeps a point from `start` to `end` against a circle that moves from
+/// `center_start` to `center_end` over the same step.
+///
+/// A point that starts inside the circle touches it at time 0.
+pub fn sweep_circle(
+    start: Vec2,
+    end: Vec2,
+    center_start: Vec2,
+    center_end: Vec2,
+    radius: f32,
+) -> Option<Contact> {
+    // Work in the circle's frame, where it stands still at the origin.
+    let relative_start = start - center_start;
+    let relative_motion = (end - center_end) - relative_start;
+
+    let c = relative_start.length_squared() - radius * radius;
+    let time = if c <= 0. {
+        0.
+    } else {
+        let a = relative_motion.length_squared();
+        if a <= f32::EPSILON {
+            return None;
+        }
+        let b = 2. * relative_start.dot(relative_motion);
+        let discriminant = b * b - 4. * a * c;
+        if discriminant < 0. {
+            return None;
+        }
+        let time = (-b - discriminant.sqrt()) / (2. * a);
+        if !(0. ..=1.).contains(&time) {
+            return None;
+        }
+        time
+    };
+
+    let point = start.lerp(end, time);
+    let center = center_start.lerp(center_end, time);
+    Some(Contact {
+        point,
+        time,
+        normal: (point - center).try_normalize().unwrap_or(Vec2::X),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RADIUS: f32 = 10.;
+
+    fn assert_close(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < 1e-3, "{a} is not close to {b}");
+    }
+
+    #[test]
+    fn fast_pass_through_the_middle_hits_the_near_edge() {
+        let contact = sweep_circle(
+            Vec2::new(-100., 0.),
+            Vec2::new(100., 0.),
+            Vec2::ZERO,
+            Vec2::ZERO,
+            RADIUS,
+        )
+        .expect("the step passes straight through the circle");
+
+        assert!((contact.time - 0.45).abs() < 1e-4);
+        assert_close(contact.point, Vec2::new(-RADIUS, 0.));
+        assert_close(contact.normal, Vec2::new(-1., 0.));
+    }
+
+    #[test]
+    fn fast_pass_that_grazes_the_edge_hits() {
+        let contact = sweep_circle(
+            Vec2::new(-100., 9.9),
+            Vec2::new(100., 9.9),
+            Vec2::ZERO,
+            Vec2::ZERO,
+            RADIUS,
+        );
+
+        assert!(contact.is_some());
+    }
+
+    #[test]
+    fn fast_pass_just_outside_misses() {
+        let contact = sweep_circle(
+            Vec2::new(-100., 10.1),
+            Vec2::new(100., 10.1),
+            Vec2::ZERO,
+            Vec2::ZERO,
+            RADIUS,
+        );
+
+        assert_eq!(contact, None);
+    }
+
+    #[test]
+    fn diagonal_pass_reports_contact_on_the_edge() {
+        let start = Vec2::new(-60., -50.);
+        let end = Vec2::new(40., 50.);
+        let center = Vec2::new(0., 5.);
+        let contact = sweep_circle(start, end, center, center, RADIUS).unwrap();
+
+        assert!((contact.point.distance(center) - RADIUS).abs() < 1e-3);
+        assert_close(contact.point, start.lerp(end, contact.time));
+    }
+
+    #[test]
+    fn stopping_short_misses() {
+        let contact = sweep_circle(
+            Vec2::new(-100., 0.),
+            Vec2::new(-10.5, 0.),
+            Vec2::ZERO,
+            Vec2::ZERO,
+            RADIUS,
+        );
+
+        assert_eq!(contact, None);
+    }
+
+    #[test]
+    fn moving_away_from_behind_misses() {
+        let contact = sweep_circle(
+            Vec2::new(20., 0.),
+            Vec2::new(200., 0.),
+            Vec2::ZERO,
+            Vec2::ZERO,
+            RADIUS,
+        );
+
+        assert_eq!(contact, None);
+    }
+
+    #[test]
+    fn starting_inside_hits_immediately() {
+        let contact = sweep_circle(
+            Vec2::new(3., 4.),
+            Vec2::new(100., 4.),
+            Vec2::ZERO,
+            Vec2::ZERO,
+            RADIUS,
+        )
+        .unwrap();
+
+        assert_eq!(contact.time, 0.);
+        assert_close(contact.point, Vec2::new(3., 4.));
+        assert_close(contact.normal, Vec2::new(0.6, 0.8));
+    }
+
+    #[test]
+    fn still_point_outside_misses() {
+        let point = Vec2::new(50., 0.);
+        assert_eq!(
+            sweep_circle(point, point, Vec2::ZERO, Vec2::ZERO, RADIUS),
+            None
+        );
+    }
+
+    #[test]
+    fn fast_circle_passing_a_still_point_hits() {
+        let point = Vec2::new(0., 5.);
+        let contact = sweep_circle(
+            point,
+            point,
+            Vec2::new(-100., 0.),
+            Vec2::new(100., 0.),
+            RADIUS,
+        )
+        .expect("the circle sweeps over the point");
+
+        assert_close(contact.point, point);
+        assert!(contact.time > 0. && contact.time < 0.5);
+        assert!((contact.normal.length() - 1.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn crossing_paths_only_hit_when_they_meet() {
+        // The point and the circle cross the origin at different times.
+        let contact = sweep_circle(
+            Vec2::new(-100., 0.),
+            Vec2::new(100., 0.),
+            Vec2::new(0., -200.),
+            Vec2::new(0., 0.),
+            RADIUS,
+        );
+        assert_eq!(contact, None);
+
+        let contact = sweep_circle(
+            Vec2::new(-100., 0.),
+            Vec2::new(100., 0.),
+            Vec2::new(0., -100.),
+            Vec2::new(0., 100.),
+            RADIUS,
+        );
+        assert!(contact.is_some());
+    }
+}
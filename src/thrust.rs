@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+
+use crate::{
+    controls::Controls,
+    effects::{ActiveEffects, EffectRegistry},
+    gravity::{GravitationTransform, FIXED_TIME_DELTA},
+    multiplayer::Multiplayer,
+    player::Player,
+    replay::{PhysicsTick, ReplayMode, ReplayRecording},
+};
+
+/// How quickly full thrust changes the player's velocity, in units per second squared.
+pub const THRUST_ACCELERATION: f32 = 120.;
+/// Fuel is measured in seconds of full thrust.
+pub const FUEL_BURN_RATE: f32 = 1.;
+pub const FUEL_TINT: Color = Color::rgb(1., 0.6, 0.2);
+
+/// The direction a player is thrusting in, with a length of at most one.
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq)]
+pub struct ThrustInput(pub Vec2);
+
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq)]
+pub struct Fuel {
+    pub remaining: f32,
+    /// Levels without any fuel capacity don't allow thrust at all.
+    pub capacity: f32,
+}
+
+impl Fuel {
+    pub fn full(capacity: f32) -> Self {
+        Self {
+            remaining: capacity,
+            capacity,
+        }
+    }
+
+    pub fn refill(&mut self) {
+        self.remaining = self.capacity;
+    }
+
+    /// Burns fuel for one fixed step of thrust towards `input`, returning the
+    /// change in velocity. Thrust tails off as the tank runs dry.
+    pub fn burn(&mut self, input: Vec2) -> Vec2 {
+        let input = input.clamp_length_max(1.);
+        let wanted = input.length() * FUEL_BURN_RATE * FIXED_TIME_DELTA;
+        if wanted <= 0. || self.remaining <= 0. {
+            return Vec2::ZERO;
+        }
+        let fraction = (self.remaining / wanted).min(1.);
+        self.remaining = (self.remaining - wanted * fraction).max(0.);
+        input * THRUST_ACCELERATION * FIXED_TIME_DELTA * fraction
+    }
+
+    pub fn label(&self) -> String {
+        if self.capacity <= 0. {
+            String::new()
+        } else {
+            format!("Fuel: {:.0}%", self.remaining / self.capacity * 100.)
+        }
+    }
+}
+
+pub(crate) fn read_thrust_input(
+    controls: Controls,
+    replay_mode: Res<ReplayMode>,
+    multiplayer: Res<Multiplayer>,
+    mut players: Query<(&Player, &mut ThrustInput)>,
+) {
+    if matches!(*replay_mode, ReplayMode::Replaying(_)) {
+        return;
+    }
+
+    for (player, mut thrust) in players.iter_mut() {
+        let input = controls.player(player.0, multiplayer.players).thrust();
+        if thrust.0 != input {
+            thrust.0 = input;
+        }
+    }
+}
+
+/// Pushes each player along with whatever thrust they hold on this tick, and
+/// records every change of input for replays.
+pub(crate) fn apply_thrust(
+    mut players: Query<(
+        &Player,
+        &ThrustInput,
+        &ActiveEffects,
+        &mut Fuel,
+        &mut GravitationTransform,
+    )>,
+    tick: Res<PhysicsTick>,
+    mut recording: ResMut<ReplayRecording>,
+    registry: Res<EffectRegistry>,
+) {
+    for (player, thrust, effects, mut fuel, mut transform) in players.iter_mut() {
+        let last_recorded = recording
+            .0
+            .thrusts
+            .iter()
+            .rev()
+            .find(|(_, recorded, _)| *recorded == player.0)
+            .map(|(_, _, input)| *input);
+        if last_recorded.unwrap_or_default() != thrust.0 {
+            recording.0.thrusts.push((tick.0, player.0, thrust.0));
+        }
+
+        if thrust.0 == Vec2::ZERO || fuel.remaining <= 0. || effects.holds_player(&registry) {
+            continue;
+        }
+        if let GravitationTransform::Velocity { velocity, .. } = transform.as_mut() {
+            *velocity += fuel.burn(thrust.0);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::FixedTimesteps;
+
+use crate::{
+    controls::{Action, Controls},
+    gravity::FIXED_TIME_MILIS,
+};
+
+/// How fast the simulation runs compared to real time. Every fixed tick still
+/// covers `FIXED_TIME_DELTA` of simulated time, so slowing down only spaces the
+/// ticks further apart and replays stay the same.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct TimeScale {
+    pub scale: f32,
+    /// Stops the fixed ticks altogether, for debugging.
+    pub paused: bool,
+    /// Stops the fixed ticks while every player is held by an effect, until
+    /// someone has something for the next tick to do.
+    pub held: bool,
+    /// Ticks to run while paused, before the next frame.
+    pub steps: u32,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self {
+            scale: 1.,
+            paused: false,
+            held: false,
+            steps: 0,
+        }
+    }
+}
+
+impl TimeScale {
+    /// The fraction of real time that passes in the simulation.
+    pub fn current(&self) -> f32 {
+        if self.paused || self.held {
+            0.
+        } else {
+            self.scale.clamp(MIN_TIME_SCALE, 1.)
+        }
+    }
+}
+
+/// Slowest speed the fixed timestep is stretched to, so it can't stall entirely.
+const MIN_TIME_SCALE: f32 = 0.05;
+
+pub(crate) fn apply_time_scale(
+    mut time_scale: ResMut<TimeScale>,
+    mut timesteps: ResMut<FixedTimesteps>,
+) {
+    let info = match timesteps.get_mut("calculate_physics") {
+        Some(info) => info,
+        None => return,
+    };
+    let step =
+        Duration::from_millis(FIXED_TIME_MILIS).div_f32(time_scale.scale.clamp(MIN_TIME_SCALE, 1.));
+    if info.step != step {
+        // Time saved up at the old speed shouldn't all be spent at once.
+        info.accumulator = info.accumulator.min(step);
+        info.step = step;
+    }
+    info.paused = time_scale.paused || time_scale.held;
+    if time_scale.steps > 0 {
+        info.accumulator += info.step * time_scale.steps;
+        time_scale.steps = 0;
+    }
+}
+
+/// Pauses the simulation and runs it one tick at a time.
+pub(crate) fn debug_time_controls(controls: Controls, mut time_scale: ResMut<TimeScale>) {
+    let controls = controls.anyone();
+    if controls.just_pressed(Action::DebugPause) {
+        time_scale.paused = !time_scale.paused;
+    }
+    if time_scale.paused && controls.just_pressed(Action::DebugStep) {
+        time_scale.steps += 1;
+    }
+}
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+
+use crate::main_camera::{CameraZoom, MainCamera};
+
+/// The finger placing a well. Once a second finger touches down, or a touch
+/// is cancelled, the touches are treated as a pinch until every finger has
+/// been lifted.
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct TouchPointer {
+    /// Where the placing finger is, in window coordinates.
+    pub position: Option<Vec2>,
+    pub just_started: bool,
+    /// The finger was lifted this frame, at `position`.
+    pub released: bool,
+    pub pinching: bool,
+}
+
+pub(crate) fn track_touches(
+    touches: Res<Touches>,
+    windows: Res<Windows>,
+    mut pointer: ResMut<TouchPointer>,
+    mut cameras: Query<&mut CameraZoom, With<MainCamera>>,
+) {
+    let height = windows
+        .get_primary()
+        .map(|w| w.height())
+        .unwrap_or_default();
+    let active = touches.iter().collect::<Vec<_>>();
+    let released = touches.iter_just_released().next();
+    let cancelled = touches.iter_just_cancelled().next().is_some();
+
+    let mut next = TouchPointer {
+        pinching: active.len() > 1
+            || cancelled
+            || (pointer.pinching && (!active.is_empty() || released.is_some())),
+        ..default()
+    };
+
+    if let [first, second, ..] = active.as_slice() {
+        let previous = first
+            .previous_position()
+            .distance(second.previous_position());
+        let current = first.position().distance(second.position());
+        if previous > 0. {
+            for mut zoom in cameras.iter_mut() {
+                zoom.pinch(current / previous);
+            }
+        }
+    } else if next.pinching {
+        // Lifting the rest of the fingers after a pinch shouldn't place anything.
+    } else if let [touch] = active.as_slice() {
+        next.position = Some(window_position(touch.position(), height));
+        next.just_started = touches.just_pressed(touch.id());
+    } else if let Some(touch) = released {
+        next.position = Some(window_position(touch.position(), height));
+        next.released = true;
+    }
+
+    *pointer = next;
+}
+
+/// Touches in a browser are measured from the top of the window, unlike the
+/// mouse cursor.
+fn window_position(position: Vec2, height: f32) -> Vec2 {
+    if cfg!(target_arch = "wasm32") {
+        Vec2::new(position.x, height - position.y)
+    } else {
+        position
+    }
+}